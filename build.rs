@@ -0,0 +1,14 @@
+use std::env;
+
+/// `env!("TARGET")` and `env!("PROFILE")` are only set for build scripts, so they are re-exported to the library as `ENVPATH_TARGET` and `ENVPATH_PROFILE`.
+///
+/// They are used by `$const: target-triple` and `$const: profile`.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    for var in ["TARGET", "PROFILE"] {
+        if let Ok(v) = env::var(var) {
+            println!("cargo:rustc-env=ENVPATH_{var}={v}");
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::EnvPath;
+use std::fmt;
+
+/// The scheme of a special chunk, which is used by [EnvPath::special()].
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Category {
+    /// `$env`
+    Env,
+    /// `$dir`
+    Dir,
+    /// `$const`
+    Const,
+    /// `$val`
+    Val,
+    /// `$proj`
+    Proj,
+}
+
+impl Category {
+    /// The scheme of the category, e.g. `$dir` for `Category::Dir`.
+    pub const fn scheme(&self) -> &'static str {
+        match self {
+            Self::Env => "$env",
+            Self::Dir => "$dir",
+            Self::Const => "$const",
+            Self::Val => "$val",
+            Self::Proj => "$proj",
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.scheme())
+    }
+}
+
+/// A builder of an `EnvPath` whose first chunk is special (e.g. `$proj(com.x.y): data`), see [EnvPath::special()].
+///
+/// It produces the canonical raw, so that a program (e.g. a directory picker) does not need to format the rules by hand.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+pub struct SpecialBuilder {
+    category: Category,
+    target: Option<String>,
+    ident: String,
+    tail: Vec<String>,
+}
+
+impl SpecialBuilder {
+    /// The project of `$proj` (e.g. `com.x.y` => `$proj(com.x.y)`). It is ignored by the other categories.
+    ///
+    /// Without it, a bare `$proj` is built, which requires [ParseOptions::default_project()](crate::ParseOptions::default_project).
+    pub fn target<S: Into<String>>(mut self, target: S) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// The ident of the special chunk (e.g. `data` => `$dir: data`).
+    pub fn ident<S: Into<String>>(mut self, ident: S) -> Self {
+        self.ident = ident.into();
+        self
+    }
+
+    /// The literal chunks after the special chunk (e.g. `["app", "cfg.toml"]`).
+    pub fn tail<S: AsRef<str>>(mut self, tail: &[S]) -> Self {
+        self.tail = tail
+            .iter()
+            .map(|x| x.as_ref().to_owned())
+            .collect();
+        self
+    }
+
+    /// Returns the canonical special chunk, e.g. `$proj(com.x.y): data`.
+    pub fn chunk(&self) -> String {
+        let ident = self.ident.trim();
+
+        match (self.category, self.target.as_deref().map(str::trim)) {
+            (Category::Proj, Some(target)) => format!("$proj({target}): {ident}"),
+            (c, _) => format!("{c}: {ident}"),
+        }
+    }
+
+    /// Builds an unresolved `EnvPath`. Use `de()` to resolve it.
+    pub fn build(self) -> EnvPath<'static> {
+        std::iter::once(self.chunk())
+            .chain(self.tail)
+            .collect()
+    }
+}
+
+impl From<SpecialBuilder> for EnvPath<'static> {
+    fn from(builder: SpecialBuilder) -> Self {
+        builder.build()
+    }
+}
+
+impl EnvPath<'_> {
+    /// Creates a builder of an `EnvPath` whose first chunk is a special chunk of `category`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{Category, EnvPath};
+    ///
+    /// let path = EnvPath::special(Category::Proj)
+    ///     .target("com.x.y")
+    ///     .ident("data")
+    ///     .tail(&["sub"])
+    ///     .build();
+    ///
+    /// assert_eq!(path.get_raw().iter().collect::<Vec<_>>(), ["$proj(com.x.y): data", "sub"]);
+    ///
+    /// let path = path.de();
+    /// dbg!(path.display());
+    /// ```
+    pub fn special(category: Category) -> SpecialBuilder {
+        SpecialBuilder {
+            category,
+            target: None,
+            ident: String::new(),
+            tail: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_raw() {
+        let raw = |b: SpecialBuilder| {
+            b.build()
+                .get_raw()
+                .iter()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            raw(EnvPath::special(Category::Proj)
+                .target(" com.x.y ")
+                .ident(" data ")
+                .tail(&["sub"])),
+            ["$proj(com.x.y): data", "sub"]
+        );
+        assert_eq!(raw(EnvPath::special(Category::Proj).ident("cfg")), ["$proj: cfg"]);
+        assert_eq!(
+            raw(EnvPath::special(Category::Dir)
+                .target("ignored")
+                .ident("cfg")),
+            ["$dir: cfg"]
+        );
+        assert_eq!(
+            raw(EnvPath::special(Category::Env).ident("home ?? userprofile")),
+            ["$env: home ?? userprofile"]
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "dirs", feature = "project", feature = "consts"))]
+    fn round_trip_through_resolution() {
+        use crate::ParseOptions;
+
+        let cases = [
+            (
+                EnvPath::special(Category::Dir)
+                    .ident("cfg")
+                    .tail(&["app"]),
+                EnvPath::new(["$dir: cfg", "app"]),
+            ),
+            (
+                EnvPath::special(Category::Const).ident("os"),
+                EnvPath::new(["$const: os"]),
+            ),
+            (
+                EnvPath::special(Category::Proj)
+                    .target("com.x.y")
+                    .ident("data")
+                    .tail(&["sub", "x.toml"]),
+                EnvPath::new(["$proj(com.x.y): data", "sub", "x.toml"]),
+            ),
+        ];
+
+        for (builder, expected) in cases {
+            let path = EnvPath::from(builder).de();
+            assert!(path.raw_eq(&expected));
+            assert_eq!(path.path, expected.path);
+            assert!(path.try_de().is_ok());
+        }
+
+        let opts = ParseOptions::new().default_project("com.x.y");
+        let path = EnvPath::special(Category::Proj)
+            .ident("data")
+            .build()
+            .de_with_options(&opts);
+        assert_eq!(path.path, EnvPath::new(["$proj(com.x.y): data"]).path);
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{
+    parser::{
+        any_ident, has_existence_check, is_random_ident, is_special_chunk, join_chunk,
+        parse_with_join, ParseCtx,
+    },
+    raw::EnvPathRaw,
+    EnvPath,
+};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    ffi::OsString,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        PoisonError, RwLock,
+    },
+};
+
+/// A cache that can be shared by many `EnvPath` resolutions.
+///
+/// Create it once and pass it to [EnvPath::de_with_cache()](crate::EnvPath::de_with_cache).
+/// Every special chunk (e.g. `$dir: cfg`, `$env: home`, `$proj(com.x.y): data`) is only looked up once during the lifetime of the cache, and subsequent resolutions reuse the result.
+///
+/// Chunks that are expected to differ at runtime are never cached, i.e. `$val` (such as `$val: rand-16`), `$dir: tmp-rand`, the current dir (`$dir: cwd`) and the existence checks of `??` (e.g. `$dir: data ?? cfg`).
+///
+/// The values are keyed on the [ParseOptions](crate::ParseOptions) of the resolution as well (e.g. the env map and the overrides), so one cache can be shared by different options without mixing their values.
+///
+/// If the environment changes, call [invalidate()](ResolverCache::invalidate) to drop the memoized values.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::{EnvPath, ResolverCache};
+///
+/// let cache = ResolverCache::new();
+///
+/// let cfg = EnvPath::from(["$dir: cfg", "a.toml"]).de_with_cache(&cache);
+/// let data = EnvPath::from(["$dir: cfg", "b.toml"]).de_with_cache(&cache);
+///
+/// dbg!(cfg.display(), data.display());
+/// assert_eq!(cache.lookups(), 1);
+///
+/// cache.invalidate();
+/// assert!(cache.is_empty());
+/// ```
+#[derive(Debug, Default)]
+pub struct ResolverCache {
+    /// fingerprint of the options => raw chunk => value
+    map: RwLock<HashMap<u64, HashMap<String, Option<OsString>>>>,
+    lookups: AtomicUsize,
+}
+
+impl ResolverCache {
+    /// Creates an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops all memoized values, the next resolution will look them up again.
+    pub fn invalidate(&self) {
+        self.map
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Drops the memoized value of a single raw chunk (e.g. `$env: home`).
+    pub fn invalidate_chunk(&self, chunk: &str) {
+        self.map
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values_mut()
+            .for_each(|m| {
+                m.remove(chunk.trim());
+            });
+    }
+
+    /// Returns the number of memoized chunks.
+    pub fn len(&self) -> usize {
+        self.map
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .map(HashMap::len)
+            .sum()
+    }
+
+    /// Returns `true` if nothing has been memoized.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns how many times the cache had to perform an underlying lookup (i.e. the number of misses).
+    pub fn lookups(&self) -> usize {
+        self.lookups.load(Ordering::Relaxed)
+    }
+
+    /// Only special chunks are worth caching, and the values that may change at runtime must not be shared.
+    pub(crate) fn is_cacheable(chunk: &str) -> bool {
+        is_special_chunk(chunk)
+            && !has_existence_check(chunk)
+            && !any_ident(chunk, is_volatile_ident)
+    }
+
+    /// Identifies the options of `ctx`, which may change the value of a chunk.
+    ///
+    /// Returns None if `ctx` carries a callback or a map without the options it belongs to (e.g. `de_with_resolver()`), which cannot be identified, so nothing is cached.
+    fn fingerprint(ctx: ParseCtx) -> Option<u64> {
+        let mut hasher = DefaultHasher::new();
+
+        match ctx.options {
+            Some(opts) => opts.hash(&mut hasher),
+            None if ctx.env_fallback.is_some()
+                || ctx.env_map.is_some()
+                || ctx.overrides.is_some() =>
+            {
+                return None
+            }
+            None => {}
+        }
+
+        Some(hasher.finish())
+    }
+
+    /// Resolves a single chunk, consulting the cache first.
+    ///
+    /// `fingerprint` is the result of `Self::fingerprint(ctx)`.
+    pub(crate) fn resolve_chunk(
+        &self,
+        chunk: &str,
+        casing: &mut String,
+        ctx: ParseCtx,
+        fingerprint: Option<u64>,
+    ) -> Option<OsString> {
+        let key = chunk.trim();
+
+        let fingerprint = match fingerprint {
+            Some(x) if Self::is_cacheable(key) => x,
+            _ => {
+                return join_chunk(Path::new(""), chunk, casing, ctx)
+                    .map(PathBuf::into_os_string)
+            }
+        };
+
+        if let Some(v) = self
+            .map
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&fingerprint)
+            .and_then(|m| m.get(key))
+        {
+            return v.clone();
+        }
+
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        let value = join_chunk(Path::new(""), chunk, casing, ctx)
+            .map(PathBuf::into_os_string);
+
+        self.map
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(fingerprint)
+            .or_default()
+            .insert(key.to_owned(), value.clone());
+
+        value
+    }
+
+    /// Similar to `parser::parse_with()`, but every chunk goes through the cache.
+    pub(crate) fn parse<S: AsRef<str>, I: IntoIterator<Item = S>>(
+        &self,
+        iter: I,
+        ctx: ParseCtx,
+    ) -> Option<PathBuf> {
+        let fingerprint = Self::fingerprint(ctx);
+
+        parse_with_join(iter, ctx, |acc_p, s, casing, ctx| {
+            self.resolve_chunk(s, casing, ctx, fingerprint)
+                .map(|p| acc_p.join(p))
+        })
+    }
+}
+
+/// Whether the value of `ident` may change at runtime, i.e. `$val`, random dirs and the current dir.
+fn is_volatile_ident(scheme: &str, ident: &str) -> bool {
+    is_random_ident(scheme, ident)
+        || scheme == "$val"
+        || (scheme == "$dir" && matches!(ident, "cwd" | "current" | "pwd"))
+}
+
+impl EnvPathRaw<'_> {
+    /// Similar to `parse()`, but memoizes the lookups in `cache`.
+    pub(crate) fn parse_with_cache(
+        &self,
+        cache: &ResolverCache,
+        ctx: ParseCtx,
+    ) -> Option<PathBuf> {
+        use EnvPathRaw::*;
+        match self {
+            Ref(x) => cache.parse(x, ctx),
+            Cow(x) => cache.parse(x, ctx),
+            Owned(x) => cache.parse(x, ctx),
+        }
+    }
+}
+
+impl EnvPath<'_> {
+    /// Similar to `de()`, but the lookups of base dirs, project dirs and env vars are memoized in a shared [ResolverCache].
+    ///
+    /// To combine it with other settings, use [ParseOptions::cache()](crate::ParseOptions::cache) instead.
+    ///
+    /// This is useful when resolving a large number of `EnvPath`s that share the same base directories.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{EnvPath, ResolverCache};
+    ///
+    /// let cache = ResolverCache::new();
+    /// let path = EnvPath::from(["$dir: data ?? cfg", "$const: deb-arch"])
+    ///     .de_with_cache(&cache);
+    /// dbg!(path.display());
+    /// ```
+    pub fn de_with_cache(self, cache: &ResolverCache) -> Self {
+        if self.raw.is_empty() {
+            return EnvPath {
+                raw: self.raw,
+                path: None,
+            };
+        }
+
+        let path = self
+            .raw
+            .parse_with_cache(cache, ParseCtx::default());
+
+        Self {
+            raw: self.raw,
+            path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn many_paths_share_one_lookup() {
+        use crate::ParseOptions;
+        use std::sync::Arc;
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let cache = Arc::new(ResolverCache::new());
+        let opts = ParseOptions::new()
+            .resolver(move |name| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                Some(OsString::from(format!("/srv/{name}")))
+            })
+            .cache(cache.clone());
+
+        let paths = (0..100)
+            .map(|i| {
+                ["$env: envpath-test-cache-unset".to_owned(), format!("app-{i}")]
+                    .into_iter()
+                    .collect::<EnvPath>()
+                    .de_with_options(&opts)
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.len(), 1);
+
+        for (i, p) in paths.iter().enumerate() {
+            assert_eq!(
+                p.path,
+                Some(PathBuf::from(format!("/srv/ENVPATH_TEST_CACHE_UNSET/app-{i}")))
+            )
+        }
+
+        cache.invalidate();
+        assert!(cache.is_empty());
+
+        EnvPath::from(["$env: envpath-test-cache-unset"]).de_with_options(&opts);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn options_do_not_share_values() {
+        use crate::ParseOptions;
+        use std::sync::Arc;
+
+        let cache = Arc::new(ResolverCache::new());
+        let de = |opts: ParseOptions, raw: &str| {
+            EnvPath::from([raw])
+                .de_with_options(&opts.cache(cache.clone()))
+                .path
+        };
+
+        let a = de(ParseOptions::new().env_map([("HOME", "/a")]), "$env: home");
+        let b = de(ParseOptions::new().env_map([("HOME", "/b")]), "$env: home");
+        assert_eq!(a, Some(PathBuf::from("/a")));
+        assert_eq!(b, Some(PathBuf::from("/b")));
+
+        let ov = de(
+            ParseOptions::new().overrides(|_| Some(PathBuf::from("/ov"))),
+            "$dir: cfg",
+        );
+        assert_eq!(ov, Some(PathBuf::from("/ov")));
+        assert_ne!(de(ParseOptions::new(), "$dir: cfg"), ov);
+
+        // Without the options, a callback cannot be identified, so nothing is cached.
+        let cache = ResolverCache::new();
+        let f = |_: &str| None;
+        let ctx = ParseCtx {
+            env_fallback: Some(&f),
+            ..Default::default()
+        };
+        cache.parse(["$env: home"], ctx);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn random_chunks_are_not_cached() {
+        let cache = ResolverCache::new();
+        EnvPath::from(["$dir: tmp-rand", "$val: empty"]).de_with_cache(&cache);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn runtime_values_are_not_cached() {
+        for chunk in [
+            "$dir: cwd",
+            "$dir: pwd",
+            "$env: dir * current",
+            "$dir: data ?? cfg",
+            "$const: none ? val * rand-8",
+            "$val: epoch",
+        ] {
+            assert!(!ResolverCache::is_cacheable(chunk), "{chunk}");
+        }
+
+        // An ident that merely contains `val` or `rand` is not special.
+        for chunk in ["$env: interval_dir", "$env: brand ? home", "$dir: cfg"] {
+            assert!(ResolverCache::is_cacheable(chunk), "{chunk}");
+        }
+        assert!(!ResolverCache::is_cacheable("cwd"));
+
+        #[cfg(feature = "unicode-separators")]
+        assert!(!ResolverCache::is_cacheable("$env: home ？？ tmp"));
+    }
+}
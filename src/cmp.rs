@@ -0,0 +1,69 @@
+use crate::EnvPath;
+use std::path::{Path, PathBuf};
+
+/// Compares the resolved path with `other`. An unresolved `EnvPath` is not equal to any path.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::EnvPath;
+/// use std::path::Path;
+///
+/// let path = EnvPath::new(["dev"]);
+/// assert_eq!(path, *Path::new("dev"));
+/// assert_eq!(path, "dev");
+///
+/// assert_ne!(EnvPath::from(["dev"]), "dev");
+/// ```
+impl PartialEq<Path> for EnvPath<'_> {
+    fn eq(&self, other: &Path) -> bool {
+        self.path.as_deref() == Some(other)
+    }
+}
+
+impl PartialEq<PathBuf> for EnvPath<'_> {
+    fn eq(&self, other: &PathBuf) -> bool {
+        *self == **other
+    }
+}
+
+impl PartialEq<str> for EnvPath<'_> {
+    fn eq(&self, other: &str) -> bool {
+        *self == *Path::new(other)
+    }
+}
+
+impl PartialEq<&str> for EnvPath<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(feature = "consts")]
+    fn eq_resolved_path() {
+        use crate::EnvPath;
+        use std::path::{Path, PathBuf};
+
+        let path = EnvPath::new(["$const: empty", "a", "b"]);
+        let expected = Path::new("a").join("b");
+
+        assert_eq!(path, expected);
+        assert_eq!(path, *expected.as_path());
+        assert_eq!(path, *expected.to_str().unwrap());
+        assert_eq!(path, expected.to_str().unwrap());
+        assert_ne!(path, "a");
+
+        // An unresolved path is not equal to any path, even an empty one.
+        let raw = EnvPath::from(["a"]);
+        assert_ne!(raw, "a");
+        assert_ne!(raw, "");
+        assert_ne!(raw, PathBuf::new());
+
+        // The derived `PartialEq` still compares both raw and path.
+        assert_ne!(path, EnvPath::new(["a", "b"]));
+        assert_eq!(path, EnvPath::new(["$const: empty", "a", "b"]));
+    }
+}
@@ -13,10 +13,17 @@
 /// | riscv64 (riscv64gc)         | riscv64        |
 /// | arm (feature = `+vfpv3`)    | armhf          |
 /// | arm                         | armel          |
+/// | arm (endian = big)          | armeb          |
 /// | mips (endian = little)      | mipsel         |
+/// | mips (endian = big)         | mips           |
 /// | mips64 (endian = little)    | mips64el       |
+/// | mips64 (endian = big)       | mips64         |
 /// | s390x                       | s390x          |
+/// | powerpc                     | powerpc        |
 /// | powerpc64 (endian = little) | ppc64el        |
+/// | powerpc64 (endian = big)    | ppc64          |
+/// | sparc64                     | sparc64        |
+/// | loongarch64                 | loong64        |
 /// | x86 (i586/i686)             | i386           |
 /// | other                       | [consts::ARCH](::std::env::consts::ARCH) |
 ///
@@ -42,24 +49,45 @@ pub const fn get_deb_arch() -> &'static str {
         #[cfg(target_arch = "riscv64")]
         () => "riscv64",
 
-        #[cfg(all(target_arch = "arm", target_feature = "vfpv3"))]
+        #[cfg(all(target_arch = "arm", target_endian = "big"))]
+        () => "armeb",
+
+        #[cfg(all(target_arch = "arm", target_endian = "little", target_feature = "vfpv3"))]
         () => "armhf",
 
-        #[cfg(all(target_arch = "arm", not(target_feature = "vfpv3")))]
+        #[cfg(all(target_arch = "arm", target_endian = "little", not(target_feature = "vfpv3")))]
         () => "armel",
 
         #[cfg(all(target_arch = "mips", target_endian = "little"))]
         () => "mipsel",
 
+        #[cfg(all(target_arch = "mips", target_endian = "big"))]
+        () => "mips",
+
         #[cfg(all(target_arch = "mips64", target_endian = "little"))]
         () => "mips64el",
 
+        #[cfg(all(target_arch = "mips64", target_endian = "big"))]
+        () => "mips64",
+
         #[cfg(target_arch = "s390x")]
         () => "s390x",
 
+        #[cfg(target_arch = "powerpc")]
+        () => "powerpc",
+
         #[cfg(all(target_arch = "powerpc64", target_endian = "little"))]
         () => "ppc64el",
 
+        #[cfg(all(target_arch = "powerpc64", target_endian = "big"))]
+        () => "ppc64",
+
+        #[cfg(target_arch = "sparc64")]
+        () => "sparc64",
+
+        #[cfg(target_arch = "loongarch64")]
+        () => "loong64",
+
         #[cfg(target_arch = "x86")]
         () => "i386",
 
@@ -68,6 +96,63 @@ pub const fn get_deb_arch() -> &'static str {
     }
 }
 
+/// Returns the name of the current target architecture in the naming scheme of another ecosystem.
+///
+/// # Table
+///
+/// | Architecture                | docker   | node    | rpm       |
+/// | --------------------------- | -------- | ------- | --------- |
+/// | x86_64                      | amd64    | x64     | x86_64    |
+/// | aarch64                     | arm64    | arm64   | aarch64   |
+/// | x86 (i586/i686)             | 386      | ia32    | i686      |
+/// | arm                         | arm      | arm     | armv7hl   |
+/// | riscv64 (riscv64gc)         | riscv64  | riscv64 | riscv64   |
+/// | powerpc64 (endian = little) | ppc64le  | ppc64   | ppc64le   |
+/// | s390x                       | s390x    | s390x   | s390x     |
+/// | other                       | [consts::ARCH](::std::env::consts::ARCH) | [consts::ARCH](::std::env::consts::ARCH) | [consts::ARCH](::std::env::consts::ARCH) |
+///
+/// `go` and `oci` are accepted as aliases of `docker`, and `nodejs` is an alias of `node`.
+///
+/// Returns `None` if the ecosystem is unknown.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::consts::get_arch_alias;
+///
+/// #[cfg(target_arch = "x86_64")]
+/// assert_eq!(get_arch_alias("node"), Some("x64"));
+///
+/// assert_eq!(get_arch_alias("unknown-ecosystem"), None);
+/// ```
+pub fn get_arch_alias(ecosystem: &str) -> Option<&'static str> {
+    use std::env::consts::ARCH;
+    let little_endian = cfg!(target_endian = "little");
+
+    let alias = match (ecosystem, ARCH) {
+        ("docker" | "go" | "oci", "x86_64") => "amd64",
+        ("docker" | "go" | "oci", "aarch64") => "arm64",
+        ("docker" | "go" | "oci", "x86") => "386",
+        ("docker" | "go" | "oci", "powerpc64") if little_endian => "ppc64le",
+        ("docker" | "go" | "oci", x) => x,
+
+        ("node" | "nodejs", "x86_64") => "x64",
+        ("node" | "nodejs", "aarch64") => "arm64",
+        ("node" | "nodejs", "x86") => "ia32",
+        ("node" | "nodejs", "powerpc64") => "ppc64",
+        ("node" | "nodejs", x) => x,
+
+        ("rpm", "x86") => "i686",
+        ("rpm", "arm") => "armv7hl",
+        ("rpm", "powerpc64") if little_endian => "ppc64le",
+        ("rpm", x) => x,
+
+        _ => return None,
+    };
+
+    Some(alias)
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -76,4 +161,50 @@ mod tests {
         let arch = super::get_deb_arch();
         dbg!(arch);
     }
+
+    #[test]
+    fn deb_arch() {
+        use super::get_deb_arch;
+
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(get_deb_arch(), "amd64");
+        #[cfg(target_arch = "aarch64")]
+        assert_eq!(get_deb_arch(), "arm64");
+        #[cfg(target_arch = "x86")]
+        assert_eq!(get_deb_arch(), "i386");
+        #[cfg(all(target_arch = "powerpc64", target_endian = "big"))]
+        assert_eq!(get_deb_arch(), "ppc64");
+        #[cfg(all(target_arch = "mips", target_endian = "big"))]
+        assert_eq!(get_deb_arch(), "mips");
+        #[cfg(target_arch = "sparc64")]
+        assert_eq!(get_deb_arch(), "sparc64");
+        #[cfg(target_arch = "loongarch64")]
+        assert_eq!(get_deb_arch(), "loong64");
+
+        // It is usable in const contexts.
+        const ARCH: &str = get_deb_arch();
+        assert!(!ARCH.is_empty());
+    }
+
+    #[test]
+    fn arch_alias() {
+        use super::get_arch_alias;
+
+        #[cfg(target_arch = "x86_64")]
+        {
+            assert_eq!(get_arch_alias("docker"), Some("amd64"));
+            assert_eq!(get_arch_alias("node"), Some("x64"));
+            assert_eq!(get_arch_alias("rpm"), Some("x86_64"));
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        {
+            assert_eq!(get_arch_alias("docker"), Some("arm64"));
+            assert_eq!(get_arch_alias("node"), Some("arm64"));
+            assert_eq!(get_arch_alias("rpm"), Some("aarch64"));
+        }
+
+        assert_eq!(get_arch_alias("go"), get_arch_alias("docker"));
+        assert_eq!(get_arch_alias("npm"), None);
+    }
 }
@@ -1,8 +1,11 @@
-use crate::{EnvPath, OsCow};
+use crate::{
+    parser::{ParseCtx, COLONS},
+    EnvPath, OsCow,
+};
 use std::{env::consts, ops::ControlFlow};
 
 mod arch;
-pub use arch::get_deb_arch;
+pub use arch::{get_arch_alias, get_deb_arch};
 
 pub const fn get_architecture() -> &'static str {
     consts::ARCH
@@ -26,14 +29,197 @@ pub const fn get_os_name() -> &'static str {
     consts::OS
 }
 
+/// The short form of [consts::OS](std::env::consts::OS), for terse folder names.
+///
+/// | OS      | short  |
+/// | ------- | ------ |
+/// | windows | win    |
+/// | macos   | mac    |
+/// | linux   | lin    |
+/// | android | and    |
+/// | ios     | ios    |
+/// | other   | [consts::OS](std::env::consts::OS) |
+///
+/// # Examples
+///
+/// ```
+/// #[cfg(target_os = "linux")]
+/// assert_eq!(envpath::consts::get_os_short(), "lin");
+/// ```
+pub const fn get_os_short() -> &'static str {
+    match () {
+        #[cfg(target_os = "windows")]
+        () => "win",
+        #[cfg(target_os = "macos")]
+        () => "mac",
+        #[cfg(target_os = "linux")]
+        () => "lin",
+        #[cfg(target_os = "android")]
+        () => "and",
+        #[cfg(target_os = "ios")]
+        () => "ios",
+        #[allow(unreachable_patterns)]
+        _ => consts::OS,
+    }
+}
+
 pub const fn get_os_family() -> &'static str {
     consts::FAMILY
 }
 
+/// A coarse category of the target OS, for choosing layout strategies.
+///
+/// | category | OS                      |
+/// | -------- | ----------------------- |
+/// | mobile   | android, ios            |
+/// | desktop  | windows, macos, linux   |
+/// | web      | wasm (e.g. `wasm32`)    |
+/// | other    | e.g. freebsd, netbsd    |
+pub const fn get_os_category() -> &'static str {
+    match () {
+        _ if cfg!(any(target_os = "android", target_os = "ios")) => "mobile",
+        _ if cfg!(any(target_os = "windows", target_os = "macos", target_os = "linux")) => {
+            "desktop"
+        }
+        _ if cfg!(target_family = "wasm") => "web",
+        _ => "other",
+    }
+}
+
+/// The target triple that envpath is compiled for (e.g. `x86_64-unknown-linux-gnu`).
+///
+/// It is captured by the build script, since `env!("TARGET")` is not available to library crates.
+pub const fn get_target_triple() -> &'static str {
+    env!("ENVPATH_TARGET")
+}
+
+/// The pointer width of the target in bits (e.g. `64`), from `cfg!(target_pointer_width)`.
+pub const fn get_pointer_width() -> &'static str {
+    if cfg!(target_pointer_width = "64") {
+        "64"
+    } else if cfg!(target_pointer_width = "32") {
+        "32"
+    } else {
+        "16"
+    }
+}
+
+/// The endianness of the target, which is either `little` or `big`.
+pub const fn get_endian() -> &'static str {
+    if cfg!(target_endian = "little") {
+        "little"
+    } else {
+        "big"
+    }
+}
+
+/// The ABI/libc of the target (e.g. `gnu`, `musl` or `msvc`), from `cfg!(target_env)`.
+///
+/// It is empty if `target_env` is unset (e.g. on macOS).
+pub const fn get_target_env() -> &'static str {
+    if cfg!(target_env = "gnu") {
+        "gnu"
+    } else if cfg!(target_env = "musl") {
+        "musl"
+    } else if cfg!(target_env = "msvc") {
+        "msvc"
+    } else if cfg!(target_env = "ohos") {
+        "ohos"
+    } else if cfg!(target_env = "uclibc") {
+        "uclibc"
+    } else if cfg!(target_env = "newlib") {
+        "newlib"
+    } else if cfg!(target_env = "sgx") {
+        "sgx"
+    } else {
+        ""
+    }
+}
+
+/// Whether the C runtime is linked statically (e.g. a static musl build), which is either `static` or `dynamic`.
+///
+/// It is derived from `cfg!(target_feature = "crt-static")`.
+pub const fn get_linkage() -> &'static str {
+    if cfg!(target_feature = "crt-static") {
+        "static"
+    } else {
+        "dynamic"
+    }
+}
+
+/// The build profile of envpath, which is either `debug` or `release`.
+///
+/// Note: It is the profile of envpath itself (as a dependency), so a custom profile is reported as the profile it inherits from.
+pub const fn get_profile() -> &'static str {
+    env!("ENVPATH_PROFILE")
+}
+
+/// Returns the comma-joined list of the envpath features that are compiled in (e.g. `consts,dirs,project`).
+///
+/// This helps bug reports show the active configuration.
+///
+/// # Examples
+///
+/// ```
+/// let features = envpath::consts::get_features();
+/// assert!(features.split(',').any(|x| x == "consts"));
+/// ```
+pub fn get_features() -> String {
+    [
+        ("consts", cfg!(feature = "consts")),
+        ("dirs", cfg!(feature = "dirs")),
+        ("project", cfg!(feature = "project")),
+        ("value", cfg!(feature = "value")),
+        ("rand", cfg!(feature = "rand")),
+        ("serde", cfg!(feature = "serde")),
+        ("unicode-separators", cfg!(feature = "unicode-separators")),
+        ("winfolder", cfg!(feature = "winfolder")),
+    ]
+    .iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| *name)
+    .collect::<Vec<_>>()
+    .join(",")
+}
+
+/// Zero-pads each numeric component of a semver-ish version string, so that the directory names are sortable.
+///
+/// A leading `v` is ignored, and the pre-release/build suffix (e.g. `-beta+1`) is kept as is.
+///
+/// Returns `None` if a component of the version is not a number.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::consts::get_padded_version;
+///
+/// assert_eq!(get_padded_version("1.2.3", 2).as_deref(), Some("01.02.03"));
+/// assert_eq!(get_padded_version("v0.10.3-rc1", 3).as_deref(), Some("000.010.003-rc1"));
+/// assert_eq!(get_padded_version("1.x", 2), None);
+/// ```
+pub fn get_padded_version(ver: &str, width: usize) -> Option<String> {
+    let ver = ver.trim();
+    let ver = ver.strip_prefix('v').unwrap_or(ver);
+
+    let (core, suffix) = match ver.find(['-', '+']) {
+        Some(i) => ver.split_at(i),
+        _ => (ver, ""),
+    };
+
+    let padded = core
+        .split('.')
+        .map(|x| x.parse::<u64>().ok().map(|n| format!("{n:0width$}")))
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(padded.join(".") + suffix)
+}
+
 impl EnvPath<'_> {
     /// This function is used to resolve ident in `$const: ident`.
     /// Although the relevant content is obtained at compile time, but wrapping it in `OsCow` is not.
-    pub(crate) fn match_consts(ident: &str) -> OsCow {
+    ///
+    /// `ctx` is only used by a remix expression (e.g. `env * home`).
+    pub(crate) fn match_consts<'a>(ident: &'a str, ctx: ParseCtx) -> OsCow<'a> {
         // Create a cow wrapper for the OS Str.
         // In fact, this is only the alias equivalent of the `os_cow()` function.
         let as_cow = crate::os_cow::from_str;
@@ -44,22 +230,44 @@ impl EnvPath<'_> {
             // "pkg_version" | "pkg-version" | "ver" => as_cow(get_pkg_version!()),
             "arch" | "architecture" => as_cow(get_architecture()),
             "deb_arch" | "deb-arch" => as_cow(get_deb_arch()),
+            x if x.starts_with("arch-alias") || x.starts_with("arch_alias") => x
+                .split_once(COLONS)
+                .and_then(|(_, ecosystem)| get_arch_alias(ecosystem.trim()))
+                .and_then(as_cow),
+            x if x.starts_with("ver-padded") || x.starts_with("ver_padded") => x
+                .split_once(COLONS)
+                .and_then(|(_, ver)| get_padded_version(ver, 2))
+                .and_then(crate::os_cow::into_os_cow),
             "os" => as_cow(get_os_name()),
+            "os-short" | "os_short" => as_cow(get_os_short()),
             "family" => as_cow(get_os_family()),
+            "os-category" | "os_category" => as_cow(get_os_category()),
+            "target-triple" | "target_triple" | "target" => as_cow(get_target_triple()),
+            "pointer-width" | "pointer_width" => as_cow(get_pointer_width()),
+            "endian" => as_cow(get_endian()),
+            "libc" | "env-abi" | "env_abi" => as_cow(get_target_env()),
+            "static" | "linkage" => as_cow(get_linkage()),
+            "profile" => as_cow(get_profile()),
+            "features" => crate::os_cow::into_os_cow(get_features()),
             "exe_suffix" => as_cow(consts::EXE_SUFFIX),
             "exe_extension" => as_cow(consts::EXE_EXTENSION),
             "empty" => as_cow(""),
-            x if Self::starts_with_remix_expr(x) => Self::parse_remix_expr(x),
+            x if Self::starts_with_remix_expr(x) => Self::parse_remix_expr(x, ctx),
             _ => None,
         }
     }
 
-    pub(crate) fn handle_consts(ident: &str) -> OsCow {
+    pub(crate) fn handle_consts<'a>(ident: &'a str, ctx: ParseCtx) -> OsCow<'a> {
         use ControlFlow::{Break, Continue};
 
         match Self::get_question_mark_separator(ident) {
-            sep if sep == ' ' => Self::match_consts(ident),
-            sep => match Self::parse_dir_rules(ident, Self::match_consts, sep) {
+            ' ' => Self::match_consts(ident, ctx),
+            sep => match Self::parse_dir_rules(
+                ident,
+                |x| Self::match_consts(x, ctx),
+                sep,
+                ctx,
+            ) {
                 Break(x) | Continue(x) => x,
             },
         }
@@ -70,6 +278,22 @@ impl EnvPath<'_> {
 mod tests {
     use crate::EnvPath;
 
+    #[test]
+    fn padded_version() {
+        use super::get_padded_version;
+
+        assert_eq!(get_padded_version("1.2.3", 2).as_deref(), Some("01.02.03"));
+        assert_eq!(get_padded_version("12.0.100", 2).as_deref(), Some("12.00.100"));
+        assert_eq!(get_padded_version("1.2.3-beta.1+b5", 2).as_deref(), Some("01.02.03-beta.1+b5"));
+        assert_eq!(get_padded_version("", 2), None);
+
+        let p = EnvPath::new(["$const: ver-padded: 1.2.3"]);
+        assert_eq!(p.to_str(), Some("01.02.03"));
+
+        let p = EnvPath::new(["$const: ver_padded: v0.4.10"]);
+        assert_eq!(p.to_str(), Some("00.04.10"));
+    }
+
     #[test]
     fn test_const_dir() {
         let v = EnvPath::from(["$const: family ?? os"]);
@@ -78,4 +302,128 @@ mod tests {
         let p = EnvPath::new(["$const: empty ?? dir * config"]);
         dbg!(p.display());
     }
+
+    #[test]
+    fn test_const_arch_alias() {
+        let p = EnvPath::new(["$const: arch-alias:docker", "$const: arch_alias : node"]);
+
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(p.path, Some(["amd64", "x64"].iter().collect()));
+
+        let p = EnvPath::new(["$const: arch-alias:unknown ? os"]);
+        assert_eq!(p.path, Some(std::env::consts::OS.into()));
+    }
+
+    #[test]
+    fn target_triple_and_profile() {
+        let p = EnvPath::new(["$const: target-triple", "$const: profile"]);
+        let c = p
+            .iter()
+            .filter_map(|x| x.to_str())
+            .collect::<Vec<_>>();
+
+        // e.g. x86_64-unknown-linux-gnu, i686-pc-windows-msvc
+        assert!(c[0].split('-').count() >= 2);
+        assert!(matches!(c[1], "debug" | "release"));
+    }
+
+    #[test]
+    fn abi_consts() {
+        let p = EnvPath::new(["$const: pointer-width"]);
+
+        #[cfg(target_pointer_width = "64")]
+        assert_eq!(p.to_str(), Some("64"));
+
+        let p = EnvPath::new(["$const: endian"]);
+
+        #[cfg(target_endian = "little")]
+        assert_eq!(p.to_str(), Some("little"));
+
+        assert!(matches!(p.to_str(), Some("little" | "big")));
+
+        assert_eq!(
+            EnvPath::new(["$const: target"]).path,
+            EnvPath::new(["$const: target_triple"]).path
+        );
+
+        // `?` fallbacks
+        let p = EnvPath::new(["$const: none ? pointer_width"]);
+        assert_eq!(p.to_str(), Some(super::get_pointer_width()));
+    }
+
+    #[test]
+    fn target_env() {
+        let p = EnvPath::new(["$const: libc"]);
+
+        #[cfg(target_env = "gnu")]
+        assert_eq!(p.to_str(), Some("gnu"));
+        #[cfg(target_env = "musl")]
+        assert_eq!(p.to_str(), Some("musl"));
+        #[cfg(target_env = "msvc")]
+        assert_eq!(p.to_str(), Some("msvc"));
+        #[cfg(target_env = "")]
+        assert_eq!(p.to_str(), Some(""));
+
+        assert_eq!(EnvPath::new(["$const: env-abi"]).path, p.path);
+    }
+
+    #[test]
+    fn linkage() {
+        let p = EnvPath::new(["$const: static"]);
+        assert!(matches!(p.to_str(), Some("static" | "dynamic")));
+
+        #[cfg(target_feature = "crt-static")]
+        assert_eq!(p.to_str(), Some("static"));
+        #[cfg(all(target_env = "gnu", not(target_feature = "crt-static")))]
+        assert_eq!(p.to_str(), Some("dynamic"));
+
+        assert_eq!(EnvPath::new(["$const: linkage"]).path, p.path);
+    }
+
+    #[test]
+    fn os_short() {
+        let p = EnvPath::new(["$const: os-short"]);
+
+        #[cfg(target_os = "linux")]
+        assert_eq!(p.to_str(), Some("lin"));
+        #[cfg(target_os = "windows")]
+        assert_eq!(p.to_str(), Some("win"));
+        #[cfg(target_os = "macos")]
+        assert_eq!(p.to_str(), Some("mac"));
+
+        assert_eq!(EnvPath::new(["$const: os_short"]).path, p.path);
+        // The full form is unchanged.
+        assert_eq!(EnvPath::new(["$const: os"]).to_str(), Some(std::env::consts::OS));
+    }
+
+    #[test]
+    fn enabled_features() {
+        let p = EnvPath::new(["$const: features"]);
+        let features = p
+            .to_str()
+            .expect("Failed to get the features")
+            .split(',')
+            .collect::<Vec<_>>();
+
+        assert!(features.contains(&"consts"));
+        assert_eq!(features.contains(&"dirs"), cfg!(feature = "dirs"));
+        assert_eq!(features.contains(&"serde"), cfg!(feature = "serde"));
+        assert_eq!(features.contains(&"rand"), cfg!(feature = "rand"));
+    }
+
+    #[test]
+    fn os_category() {
+        let p = EnvPath::new(["$const: os-category"]);
+
+        #[cfg(any(target_os = "linux", target_os = "windows", target_os = "macos"))]
+        assert_eq!(p.to_str(), Some("desktop"));
+
+        #[cfg(target_os = "android")]
+        assert_eq!(p.to_str(), Some("mobile"));
+
+        assert!(matches!(
+            p.to_str(),
+            Some("mobile" | "desktop" | "web" | "other")
+        ));
+    }
 }
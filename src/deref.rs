@@ -1,6 +1,9 @@
 use crate::EnvPath;
 use core::ops::{Deref, DerefMut};
-use std::path::{Path, PathBuf};
+use std::{
+    ffi::OsStr,
+    path::{Components, Path, PathBuf},
+};
 
 /// This implementation allows for mutable access to the underlying path value of `EnvPath`.
 impl<'r> DerefMut for EnvPath<'r> {
@@ -27,3 +30,127 @@ impl<'r> Deref for EnvPath<'r> {
         }
     }
 }
+
+/// Same as `Deref`, so an `EnvPath` can be passed to `fs::read(&path)` or `File::open(&path)` directly.
+impl AsRef<Path> for EnvPath<'_> {
+    fn as_ref(&self) -> &Path {
+        self
+    }
+}
+
+/// Same as `Deref`, so an `EnvPath` can be passed to `Command::arg(&path)` directly.
+impl AsRef<OsStr> for EnvPath<'_> {
+    fn as_ref(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl EnvPath<'_> {
+    /// Returns the components of the resolved path, or `None` if it has not been resolved.
+    ///
+    /// Unlike `Deref` (which falls back to an empty path), this makes it impossible to mistake an unresolved `EnvPath` for an empty one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    /// use std::path::Component;
+    ///
+    /// let path = EnvPath::new(["a", "b"]);
+    /// let names = path
+    ///     .components()
+    ///     .map(|c| c.map(Component::as_os_str).collect::<Vec<_>>());
+    /// assert_eq!(names, Some(vec!["a".as_ref(), "b".as_ref()]));
+    ///
+    /// assert!(EnvPath::from(["$env: home"]).components().is_none());
+    /// ```
+    pub fn components(&self) -> Option<Components<'_>> {
+        self.path
+            .as_deref()
+            .map(Path::components)
+    }
+
+    /// Returns the resolved path, or `None` if it has not been resolved.
+    ///
+    /// Unlike `Deref` (which falls back to an empty path), this distinguishes "resolved to empty" from "not resolved".
+    ///
+    /// Note: `de()` falls back to the raw str for a chunk that cannot be resolved (e.g. `$dir: none_such`), so the path is only `None` before resolution, or if the raw sequence is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "consts")] {
+    /// use envpath::EnvPath;
+    /// use std::path::Path;
+    ///
+    /// let path = EnvPath::new(["$const: empty"]);
+    /// assert_eq!(path.resolved(), Some(Path::new("")));
+    ///
+    /// assert_eq!(EnvPath::from(["$const: empty"]).resolved(), None);
+    /// # }
+    /// ```
+    pub fn resolved(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns `true` if the path has been resolved, i.e. `resolved()` is `Some`.
+    pub fn is_resolved(&self) -> bool {
+        self.path.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(feature = "consts", feature = "dirs"))]
+    use crate::EnvPath;
+
+    #[test]
+    #[cfg(feature = "consts")]
+    fn components_of_unresolved_path() {
+        use std::path::Component;
+
+        assert!(EnvPath::from(["a", "b"]).components().is_none());
+        assert!(EnvPath::default().components().is_none());
+
+        let path = EnvPath::new(["a", "$const: empty", "b"]);
+        let components = path
+            .components()
+            .expect("The path should be resolved")
+            .collect::<Vec<_>>();
+        assert_eq!(
+            components,
+            [Component::Normal("a".as_ref()), Component::Normal("b".as_ref())]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "consts")]
+    fn resolved_or_not() {
+        let unknown = EnvPath::from(["$dir: envpath-none", "a"]);
+        assert_eq!(unknown.resolved(), None);
+        assert!(!unknown.is_resolved());
+        // `Deref` hides the difference.
+        assert_eq!(unknown.as_os_str(), "");
+
+        assert_eq!(EnvPath::default().de().resolved(), None);
+
+        let empty = EnvPath::new(["$const: empty"]);
+        assert!(empty.is_resolved());
+        assert_eq!(empty.resolved(), Some(std::path::Path::new("")));
+    }
+
+    #[test]
+    #[cfg(feature = "dirs")]
+    fn as_ref_path_and_os_str() {
+        use std::{ffi::OsStr, path::Path, process::Command};
+
+        let tmp = EnvPath::new(["$dir: tmp"]);
+        assert!(std::fs::metadata(&tmp).is_ok());
+
+        assert_eq!(AsRef::<Path>::as_ref(&tmp), tmp.path.as_deref().unwrap());
+        let _ = Command::new("echo").arg(&tmp);
+
+        let raw = EnvPath::from(["$env: home"]);
+        assert_eq!(AsRef::<OsStr>::as_ref(&raw), "");
+    }
+}
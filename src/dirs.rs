@@ -1,8 +1,24 @@
 use crate::{
     os_cow::{self, into_os_cow},
+    parser::ParseCtx,
     EnvPath, OsCow,
 };
-use std::{borrow::Cow, env, ops::ControlFlow, path::PathBuf};
+use std::{
+    borrow::Cow,
+    env,
+    ffi::{OsStr, OsString},
+    ops::ControlFlow,
+    path::PathBuf,
+};
+
+/// Gets the value of an environment variable, i.e. from the process environment, or from the map of [EnvPath::de_with_env()].
+pub(crate) type EnvLookup<'e> = &'e dyn Fn(&str) -> Option<OsString>;
+
+/// Gets the value of an environment variable from the process environment.
+pub(crate) fn process_env(var: &str) -> Option<OsString> {
+    env::var_os(var)
+}
+
 impl EnvPath<'_> {
     /// Returns the path to the `Microsoft` directory in the local data folder on Windows, if available.
     ///
@@ -12,8 +28,10 @@ impl EnvPath<'_> {
     ///
     /// An `Option<PathBuf>` object containing the path to the `Microsoft` directory, or `None` if it is unavailable.
     #[cfg(windows)] // This function is only available on Windows
-    pub(crate) fn get_microsoft_windows_data_dir() -> Option<PathBuf> {
-        dirs::data_local_dir().map(|x| x.join("Microsoft")) // Gets the path to the local data directory and appends "Microsoft" to it
+    pub(crate) fn get_microsoft_windows_data_dir<B: DirsBackend + ?Sized>(
+        b: &B,
+    ) -> Option<PathBuf> {
+        b.data_local_dir().map(|x| x.join("Microsoft")) // Gets the path to the local data directory and appends "Microsoft" to it
     }
 
     /// Returns the path to the executable directory or the `WindowsApps` directory on Windows.
@@ -25,21 +43,21 @@ impl EnvPath<'_> {
     /// | unix (if available) | `$env: xdg_bin_home`                                      |
     /// | unix                | `/home/[username]/.local/bin`                             |
     ///
-    pub(crate) fn set_bin_dir<'a>() -> OsCow<'a> {
+    pub(crate) fn set_bin_dir<'a, B: DirsBackend + ?Sized>(b: &B) -> OsCow<'a> {
         let bin_dir =
-            || dirs::data_local_dir().and_then(|p| into_os_cow(p.join("bin"))); // Gets the path to the local data directory and appends "bin" to it, wrapped in an OsCow object
+            || b.data_local_dir().and_then(|p| into_os_cow(p.join("bin"))); // Gets the path to the local data directory and appends "bin" to it, wrapped in an OsCow object
 
-        match dirs::executable_dir() {
+        match b.executable_dir() {
             // Checks if there is an executable directory
             Some(s) => into_os_cow(s), // If there is, return it wrapped in an OsCow object
             #[cfg(windows)]
-            _ => match Self::get_microsoft_windows_data_dir() {
+            _ => match Self::get_microsoft_windows_data_dir(b) {
                 // If on Windows, check if the Microsoft directory is Some(x).
                 Some(x) => into_os_cow(x.join("WindowsApps")), // If it is, return the path to the WindowsApps directory wrapped in an OsCow object
                 _ => bin_dir(), // Otherwise, return the bin directory wrapped in an OsCow object
             },
             #[cfg(unix)]
-            _ => match dirs::home_dir() {
+            _ => match b.home_dir() {
                 // If on Unix, get the path to the home directory
                 Some(x) => into_os_cow(x.join(".local/bin")), // Append ".local/bin" to it and return it wrapped in an OsCow object
                 _ => bin_dir(), // If the home directory is unavailable, return the bin directory wrapped in an OsCow object
@@ -50,26 +68,124 @@ impl EnvPath<'_> {
     }
 
     /// Returns the path to the system fonts directory on Windows, or the `fonts` directory in the system data directory on Unix-like systems.
-    pub(crate) fn set_font_dir<'a>() -> OsCow<'a> {
-        match dirs::font_dir() {
+    pub(crate) fn set_font_dir<'a, B: DirsBackend + ?Sized>(b: &B) -> OsCow<'a> {
+        match b.font_dir() {
             // Checks if there is a font directory
             Some(s) => into_os_cow(s), // If there is, return it wrapped in an OsCow object
             #[cfg(windows)]
-            _ => match Self::get_microsoft_windows_data_dir() {
+            _ => match Self::get_microsoft_windows_data_dir(b) {
                 // If on Windows, check if the Microsoft directory is available
                 Some(x) => into_os_cow(x.join(r#"Windows\Fonts"#)), // If it is, return the path to the Windows fonts directory wrapped in an OsCow object
                 _ => os_cow::from_str(r#"C:\Windows\Fonts"#), // Otherwise, return the path to the Windows fonts directory wrapped in an OsCow object
             },
             #[cfg(unix)]
-            _ => dirs::data_dir().and_then(|p| into_os_cow(p.join("fonts"))), // If on Unix, get the path to the system data directory and append "fonts" to it, then return it wrapped in an OsCow object
+            _ => b.data_dir().and_then(|p| into_os_cow(p.join("fonts"))), // If on Unix, get the path to the system data directory and append "fonts" to it, then return it wrapped in an OsCow object
             #[cfg(not(any(unix, windows)))]
             _ => None, // If not on Unix or Windows, return None
         }
     }
 
+    /// Returns the log directory of a standalone tool, `[pkg]` is the file stem of the current executable.
+    ///
+    /// | Platform | Example                          |
+    /// | -------- | -------------------------------- |
+    /// | linux    | `$xdg_state_home/[pkg]/logs`     |
+    /// | macos    | `$home/Library/Logs/[pkg]`       |
+    /// | windows  | `$home\AppData\Local\[pkg]\logs` |
+    pub(crate) fn set_log_dir<'a, B: DirsBackend + ?Sized>(b: &B) -> OsCow<'a> {
+        let pkg = get_exe_stem()?;
+
+        let dir = match () {
+            #[cfg(target_os = "macos")]
+            () => b.home_dir().map(|x| x.join("Library/Logs").join(pkg)),
+            #[cfg(windows)]
+            () => b.data_local_dir().map(|x| x.join(pkg).join("logs")),
+            #[cfg(not(any(target_os = "macos", windows)))]
+            () => b.state_dir().map(|x| x.join(pkg).join("logs")),
+        };
+        dir.and_then(into_os_cow)
+    }
+
+    /// Returns the XDG base directory of `var` (e.g. `XDG_DATA_HOME`), following the XDG Base Directory Specification.
+    ///
+    /// If the environment variable is unset, empty or relative, the spec default (`$home/[default]`) is used. `XDG_RUNTIME_DIR` has no default, so `default` is None.
+    ///
+    /// It is only available on Unix, other platforms return None, so that the `??` fallback works.
+    pub(crate) fn set_xdg_dir<'a, B: DirsBackend + ?Sized>(
+        var: &str,
+        default: Option<&str>,
+        b: &B,
+        env: EnvLookup,
+    ) -> OsCow<'a> {
+        match () {
+            #[cfg(unix)]
+            () => env(var)
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+                .or_else(|| b.home_dir().zip(default).map(|(h, d)| h.join(d)))
+                .and_then(into_os_cow),
+            #[cfg(not(unix))]
+            () => {
+                let _ = (var, default, b, env);
+                None
+            }
+        }
+    }
+
+    /// Returns a subdirectory of the download directory (`$dir: dl`), e.g. `[dl]/Incoming` for file-transfer apps.
+    ///
+    /// Returns None if the download directory is unavailable.
+    pub(crate) fn set_download_subdir<'a, B: DirsBackend + ?Sized>(
+        name: &str,
+        b: &B,
+    ) -> OsCow<'a> {
+        Self::set_dir(|| b.download_dir(), "Download")
+            .map(|x| PathBuf::from(x.into_owned()).join(name))
+            .and_then(into_os_cow)
+    }
+
+    /// Returns the trash directory of the current user.
+    ///
+    /// | Platform | Trash                  |
+    /// | -------- | ---------------------- |
+    /// | linux    | `$xdg_data_home/Trash` |
+    /// | macOS    | `$home/.Trash`         |
+    ///
+    /// On other platforms (e.g. Windows, where the Recycle Bin is a per-drive virtual folder), it cannot be determined, so None is returned.
+    pub(crate) fn set_trash_dir<'a, B: DirsBackend + ?Sized>(b: &B) -> OsCow<'a> {
+        let trash = match () {
+            #[cfg(target_os = "macos")]
+            () => b.home_dir().map(|x| x.join(".Trash")),
+            #[cfg(all(unix, not(any(target_os = "macos", target_os = "android"))))]
+            () => b.data_dir().map(|x| x.join("Trash")),
+            #[allow(unreachable_patterns)]
+            () => {
+                let _ = b;
+                None
+            }
+        };
+        trash.and_then(into_os_cow)
+    }
+
+    /// Returns the home directory of a Rust tool (e.g. `$CARGO_HOME`), or `$home/[default]` if `var` is unset or empty.
+    ///
+    /// Unlike `set_xdg_dir()`, it is available on all platforms.
+    pub(crate) fn set_tool_home<'a, B: DirsBackend + ?Sized>(
+        var: &str,
+        default: &str,
+        b: &B,
+        env: EnvLookup,
+    ) -> OsCow<'a> {
+        env(var)
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| b.home_dir().map(|h| h.join(default)))
+            .and_then(into_os_cow)
+    }
+
     /// Returns either the first or last path in the `PATH` environment variable.
-    pub(crate) fn set_double_ended_path(s: &str) -> OsCow {
-        let Some(path) = env::var_os("PATH") else { // Gets the value of the PATH environment variable, or returns None if it is unavailable
+    pub(crate) fn set_double_ended_path<'a>(s: &str, env: EnvLookup) -> OsCow<'a> {
+        let Some(path) = env("PATH") else { // Gets the value of the PATH environment variable, or returns None if it is unavailable
             return None // If PATH is unavailable, return None
         };
         let path_iter = || env::split_paths(&path); // Splits the PATH variable into multiple paths
@@ -91,7 +207,7 @@ impl EnvPath<'_> {
     /// | --- | --- |
     /// | Parameters | <table><tr><td>`p`:</td><td>A function that returns an `Option<PathBuf>` object.</td></tr> <tr><td>`_android_dir`:</td><td>A string representing the Android-specific directory to use. For non-Android platforms, to avoid the "unused variable" warning, I added the `_` prefix to the variable name.</td></tr></table> |
     /// | Returns | The path to the directory specified by the given function, or the Android-specific directory if running on Android. |
-    pub(crate) fn set_dir<F>(p: F, _android_dir: &str) -> OsCow
+    pub(crate) fn set_dir<F>(p: F, _android_dir: &str) -> OsCow<'_>
     where
         F: FnOnce() -> Option<PathBuf>,
     {
@@ -103,53 +219,152 @@ impl EnvPath<'_> {
         }
     }
 
-    pub(crate) fn handle_dirs(ident: &str) -> OsCow {
+    pub(crate) fn handle_dirs<'a>(ident: &'a str, ctx: ParseCtx) -> OsCow<'a> {
         use ControlFlow::{Break, Continue};
 
         match Self::get_question_mark_separator(ident) {
-            sep if sep == ' ' => Self::match_base_dirs(ident),
-            sep => match Self::parse_dir_rules(ident, Self::match_base_dirs, sep) {
+            ' ' => Self::match_base_dirs_ctx(ident, ctx),
+            sep => match Self::parse_dir_rules(
+                ident,
+                |x| Self::match_base_dirs_ctx(x, ctx),
+                sep,
+                ctx,
+            ) {
                 Break(x) | Continue(x) => x,
             },
         }
     }
 
+    /// Takes the options of `ctx` into account (i.e. [ParseOptions::respect_sudo_user()](crate::ParseOptions::respect_sudo_user) and [ParseOptions::env_first()](crate::ParseOptions::env_first)), and then matches the base directory.
+    ///
+    /// A remix expression (e.g. `env * home`) is resolved with the same `ctx`.
+    pub(crate) fn match_base_dirs_ctx<'a>(ident: &'a str, ctx: ParseCtx) -> OsCow<'a> {
+        if Self::starts_with_remix_expr(ident) {
+            return Self::parse_remix_expr(ident, ctx);
+        }
+
+        let opt = |f: fn(&crate::ParseOptions) -> bool| ctx.options.is_some_and(f);
+        let mut backend: &dyn DirsBackend = &SystemDirs;
+
+        let env = |var: &str| match ctx.env_map {
+            Some(map) => map.get_var(var),
+            _ => process_env(var),
+        };
+        let env_first;
+        if opt(|o| o.env_first) {
+            env_first = EnvFirstDirs {
+                inner: backend,
+                env: &env,
+            };
+            backend = &env_first;
+        }
+
+        // The home of `SUDO_USER` is applied last, since `HOME` is usually the home of root under sudo.
+        #[cfg(unix)]
+        let sudo;
+        #[cfg(unix)]
+        if opt(|o| o.respect_sudo_user) {
+            if let Some(home) = crate::sudo::sudo_user_home(&env) {
+                sudo = crate::sudo::SudoUserDirs {
+                    home,
+                    inner: backend,
+                };
+                backend = &sudo;
+            }
+        }
+
+        Self::match_base_dirs_with(ident, backend, &env)
+    }
+
+    /// Whether `ident` is a known ident of `$dir`, even if it cannot be resolved on this machine (e.g. `dirs` cannot find `cfg`, or `XDG_RUNTIME_DIR` is unset).
+    ///
+    /// An ident that is unavailable on the current platform (e.g. `saved-games` on Linux) is unknown.
+    pub(crate) fn is_known_dir(ident: &str) -> bool {
+        KNOWN_DIRS.contains(&ident)
+    }
+
     /// Use `match` to match **ident** in `$dir: ident` and get different Paths depending on the platform.
     /// This is the core function of this module.
-    pub(crate) fn match_base_dirs(ident: &str) -> OsCow {
-        use dirs::*;
+    ///
+    /// The base directories (e.g. `cache`) are obtained from `b` (e.g. [SystemDirs]), rather than from `dirs` directly, and the environment variables (e.g. `XDG_CONFIG_HOME`) are obtained from `env`.
+    pub(crate) fn match_base_dirs_with<'a, B: DirsBackend + ?Sized>(
+        ident: &'a str,
+        b: &B,
+        env: EnvLookup,
+    ) -> OsCow<'a> {
         let into_cow = |p: Option<PathBuf>| p.and_then(into_os_cow);
 
         match ident {
-            "music" | "audio" => Self::set_dir(audio_dir, "Music"),
-            "cache" => into_cow(cache_dir()),
-            "cfg" | "config" => into_cow(config_dir()),
-            "data" => into_cow(data_dir()),
+            "music" | "audio" => Self::set_dir(|| b.audio_dir(), "Music"),
+            "cache" => into_cow(b.cache_dir()),
+            "cfg" | "config" => into_cow(b.config_dir()),
+            "data" => into_cow(b.data_dir()),
             "local_data" | "local-data" => {
-                Self::set_dir(data_local_dir, "Android/data")
+                Self::set_dir(|| b.data_local_dir(), "Android/data")
             }
             "local-cfg" | "local_cfg" | "local_config" => {
-                Self::set_dir(config_local_dir, "Android/data")
+                Self::set_dir(|| b.config_local_dir(), "Android/data")
             }
-            "desktop" => into_cow(desktop_dir()),
+            "desktop" => into_cow(b.desktop_dir()),
             "doc" | "document" | "documentation" => {
-                Self::set_dir(document_dir, "Documents")
+                Self::set_dir(|| b.document_dir(), "Documents")
             }
-            "dl" | "download" => Self::set_dir(download_dir, "Download"),
-            "bin" | "exe" | "executable" => Self::set_bin_dir(),
+            "dl" | "download" | "downloads" => Self::set_dir(|| b.download_dir(), "Download"),
+            "incoming" => Self::set_download_subdir("Incoming", b),
+            "outgoing" => Self::set_download_subdir("Outgoing", b),
+            #[cfg(windows)]
+            "saved-games" | "saved_games" => into_cow(b.saved_games_dir()),
+            "bin" | "exe" | "executable" => Self::set_bin_dir(b),
             "path" | "first-path" | "first_path" => {
-                Self::set_double_ended_path("first")
+                Self::set_double_ended_path("first", env)
+            }
+            "last_path" | "last-path" => Self::set_double_ended_path("last", env),
+            "font" | "typeface" => Self::set_font_dir(b),
+            "home" => into_cow(b.home_dir()),
+            "pic" | "picture" => Self::set_dir(|| b.picture_dir(), "Pictures"),
+            "pref" | "preference" => into_cow(b.preference_dir()),
+            "pub" | "public" => into_cow(b.public_dir()),
+            "runtime" => into_cow(b.runtime_dir()),
+            "state" => into_cow(b.state_dir()),
+            #[cfg(unix)]
+            "socket" | "sock" => {
+                into_cow(b.runtime_dir()).or_else(|| os_cow::from_str("/tmp"))
             }
-            "last_path" | "last-path" => Self::set_double_ended_path("last"),
-            "font" | "typeface" => Self::set_font_dir(),
-            "home" => into_cow(home_dir()),
-            "pic" | "picture" => Self::set_dir(audio_dir, "Pictures"),
-            "pref" | "preference" => into_cow(preference_dir()),
-            "pub" | "public" => into_cow(public_dir()),
-            "runtime" => into_cow(runtime_dir()),
-            "state" => into_cow(state_dir()),
-            "template" => into_cow(template_dir()),
-            "video" | "movie" => Self::set_dir(video_dir, "Movies"),
+            "log" | "logs" => Self::set_log_dir(b),
+            "trash" => Self::set_trash_dir(b),
+            "xdg-config-home" | "xdg_config_home" => {
+                Self::set_xdg_dir("XDG_CONFIG_HOME", Some(".config"), b, env)
+            }
+            "xdg-data-home" | "xdg_data_home" => {
+                Self::set_xdg_dir("XDG_DATA_HOME", Some(".local/share"), b, env)
+            }
+            "xdg-cache-home" | "xdg_cache_home" => {
+                Self::set_xdg_dir("XDG_CACHE_HOME", Some(".cache"), b, env)
+            }
+            "xdg-state-home" | "xdg_state_home" => {
+                Self::set_xdg_dir("XDG_STATE_HOME", Some(".local/state"), b, env)
+            }
+            "xdg-runtime-dir" | "xdg_runtime_dir" => {
+                Self::set_xdg_dir("XDG_RUNTIME_DIR", None, b, env)
+            }
+            "cargo-home" | "cargo_home" => Self::set_tool_home("CARGO_HOME", ".cargo", b, env),
+            "rustup-home" | "rustup_home" => Self::set_tool_home("RUSTUP_HOME", ".rustup", b, env),
+            "cwd" | "current" | "pwd" => env::current_dir()
+                .ok()
+                .and_then(into_os_cow),
+            "self-exe" | "self_exe" | "current-exe" | "current_exe" => {
+                env::current_exe()
+                    .ok()
+                    .and_then(into_os_cow)
+            }
+            "self-exe-dir" | "self_exe_dir" | "current-exe-dir" | "current_exe_dir" => {
+                env::current_exe()
+                    .ok()
+                    .and_then(|x| x.parent().and_then(into_os_cow))
+            }
+            "scratch" => into_cow(join_scratch_dir(b.runtime_dir(), std::process::id())),
+            "template" => into_cow(b.template_dir()),
+            "video" | "movie" => Self::set_dir(|| b.video_dir(), "Movies"),
             "tmp" => into_os_cow(get_tmp_dir()),
             #[cfg(feature = "rand")]
             "tmp-rand" | "tmp_random" => into_os_cow(get_tmp_random_dir(None, None)),
@@ -157,44 +372,445 @@ impl EnvPath<'_> {
             #[cfg(target_os = "android")]
             "sd" => os_cow::from_str(os_cow::AND_SD),
             #[cfg(windows)]
-            "local-low" | "local_low" => into_cow(data_local_dir().and_then(|p| {
+            "local-low" | "local_low" => into_cow(b.data_local_dir().and_then(|p| {
                 p.parent()
                     .map(|x| x.join("LocalLow"))
             })),
-            "cli-data" | "cli_data" => into_cow(data_local_dir()),
-            "cli-cfg" | "cli_cfg" | "cli_config" => into_cow(config_local_dir()),
-            "cli-cache" | "cli_cache" => into_cow(cache_dir()),
+            // The explicit names of `data` and `local-data` on Windows.
+            #[cfg(windows)]
+            "appdata-roaming" | "appdata_roaming" => into_cow(b.data_dir()),
+            #[cfg(windows)]
+            "appdata-local" | "appdata_local" => into_cow(b.data_local_dir()),
+            "cli-data" | "cli_data" => into_cow(b.data_local_dir()),
+            "cli-cfg" | "cli_cfg" | "cli_config" => into_cow(b.config_local_dir()),
+            "cli-cache" | "cli_cache" => into_cow(b.cache_dir()),
+            // See the Thumbnail Managing Standard of freedesktop.
+            #[cfg(target_os = "linux")]
+            "thumbnails" | "thumbnail" => {
+                into_cow(b.cache_dir().map(|x| x.join("thumbnails")))
+            }
             #[cfg(windows)]
-            "progam-files" | "program_files" => Self::into_os_env("ProgramFiles")
+            "progam-files" | "program_files" => env("ProgramFiles").and_then(into_os_cow)
                 .or_else(|| os_cow::from_str(r#"C:\Program Files"#)),
             #[cfg(windows)]
             "program-files-x86" | "program_files_x86" => {
-                Self::into_os_env("ProgramFiles(x86)")
+                env("ProgramFiles(x86)").and_then(into_os_cow)
                     .or_else(|| os_cow::from_str(r#"=C:\Program Files (x86)"#))
             }
             #[cfg(windows)]
             "common-program-files" | "common_program_files" => {
-                Self::into_os_env("CommonProgramFiles")
+                env("CommonProgramFiles").and_then(into_os_cow)
                     .or_else(|| os_cow::from_str(r#"C:\Program Files\Common Files"#))
             }
             #[cfg(windows)]
             "common-program-files-x86" | "common_program_files_x86" => {
-                Self::into_os_env("CommonProgramFiles(x86)").or_else(|| {
+                env("CommonProgramFiles(x86)").and_then(into_os_cow).or_else(|| {
                     os_cow::from_str(r#"C:\Program Files (x86)\Common Files"#)
                 })
             }
             #[cfg(windows)]
-            "program-data" | "program_data" => Self::into_os_env("ProgramData")
+            "program-data" | "program_data" => env("ProgramData").and_then(into_os_cow)
                 .or_else(|| os_cow::from_str(r#"C:\ProgramData"#)),
             #[cfg(windows)]
-            "microsoft" => into_cow(data_dir().map(|x| x.join("Microsoft"))),
+            "microsoft" => into_cow(b.data_dir().map(|x| x.join("Microsoft"))),
+            #[cfg(unix)]
+            "root" => os_cow::from_str("/"),
+            #[cfg(windows)]
+            "root" => env("SystemDrive").and_then(into_os_cow)
+                .and_then(|x| into_os_cow(PathBuf::from(x.into_owned()).join(r#"\"#)))
+                .or_else(|| os_cow::from_str(r#"C:\"#)),
             "empty" => os_cow::from_str(""),
-            x if Self::starts_with_remix_expr(x) => Self::parse_remix_expr(x),
             _ => None,
         }
     }
 }
 
+/// The source of the base directories of `$dir` (e.g. `cache`).
+///
+/// By default, each method calls the function of the same name in `dirs`, so that the tests can override only the directories that they need.
+pub(crate) trait DirsBackend {
+    fn home_dir(&self) -> Option<PathBuf> {
+        dirs::home_dir()
+    }
+    fn cache_dir(&self) -> Option<PathBuf> {
+        dirs::cache_dir()
+    }
+    fn config_dir(&self) -> Option<PathBuf> {
+        dirs::config_dir()
+    }
+    fn config_local_dir(&self) -> Option<PathBuf> {
+        dirs::config_local_dir()
+    }
+    fn data_dir(&self) -> Option<PathBuf> {
+        dirs::data_dir()
+    }
+    fn data_local_dir(&self) -> Option<PathBuf> {
+        dirs::data_local_dir()
+    }
+    fn preference_dir(&self) -> Option<PathBuf> {
+        dirs::preference_dir()
+    }
+    fn runtime_dir(&self) -> Option<PathBuf> {
+        dirs::runtime_dir()
+    }
+    fn state_dir(&self) -> Option<PathBuf> {
+        dirs::state_dir()
+    }
+    fn audio_dir(&self) -> Option<PathBuf> {
+        dirs::audio_dir()
+    }
+    fn desktop_dir(&self) -> Option<PathBuf> {
+        dirs::desktop_dir()
+    }
+    fn document_dir(&self) -> Option<PathBuf> {
+        dirs::document_dir()
+    }
+    fn download_dir(&self) -> Option<PathBuf> {
+        dirs::download_dir()
+    }
+    fn executable_dir(&self) -> Option<PathBuf> {
+        dirs::executable_dir()
+    }
+    fn font_dir(&self) -> Option<PathBuf> {
+        dirs::font_dir()
+    }
+    fn picture_dir(&self) -> Option<PathBuf> {
+        dirs::picture_dir()
+    }
+    fn public_dir(&self) -> Option<PathBuf> {
+        dirs::public_dir()
+    }
+    fn template_dir(&self) -> Option<PathBuf> {
+        dirs::template_dir()
+    }
+    fn video_dir(&self) -> Option<PathBuf> {
+        dirs::video_dir()
+    }
+    /// `$home\Saved Games`, since `dirs` does not provide it.
+    #[cfg(windows)]
+    fn saved_games_dir(&self) -> Option<PathBuf> {
+        dirs::home_dir().map(|x| x.join("Saved Games"))
+    }
+}
+
+/// The real base directories, which are obtained from `dirs`.
+///
+/// With the `winfolder` feature, `desktop`, `doc`, `dl` and `saved-games` are obtained via `SHGetKnownFolderPath` on Windows, which honors the redirection (e.g. into OneDrive). If the call fails, `dirs` is used.
+pub(crate) struct SystemDirs;
+
+#[cfg(not(all(windows, feature = "winfolder")))]
+impl DirsBackend for SystemDirs {}
+
+#[cfg(all(windows, feature = "winfolder"))]
+impl DirsBackend for SystemDirs {
+    fn desktop_dir(&self) -> Option<PathBuf> {
+        crate::winfolder::desktop_dir().or_else(dirs::desktop_dir)
+    }
+    fn document_dir(&self) -> Option<PathBuf> {
+        crate::winfolder::documents_dir().or_else(dirs::document_dir)
+    }
+    fn download_dir(&self) -> Option<PathBuf> {
+        crate::winfolder::downloads_dir().or_else(dirs::download_dir)
+    }
+    fn saved_games_dir(&self) -> Option<PathBuf> {
+        crate::winfolder::saved_games_dir()
+            .or_else(|| dirs::home_dir().map(|x| x.join("Saved Games")))
+    }
+}
+
+/// The idents of `$dir` that are available on the current platform, i.e. the arms of [EnvPath::match_base_dirs_with()].
+const KNOWN_DIRS: &[&str] = &[
+    "music", "audio",
+    "cache",
+    "cfg", "config",
+    "data",
+    "local_data", "local-data",
+    "local-cfg", "local_cfg", "local_config",
+    "desktop",
+    "doc", "document", "documentation",
+    "dl", "download", "downloads",
+    "incoming", "outgoing",
+    #[cfg(windows)]
+    "saved-games",
+    #[cfg(windows)]
+    "saved_games",
+    "bin", "exe", "executable",
+    "path", "first-path", "first_path", "last_path", "last-path",
+    "font", "typeface",
+    "home",
+    "pic", "picture",
+    "pref", "preference",
+    "pub", "public",
+    "runtime",
+    "state",
+    #[cfg(unix)]
+    "socket",
+    #[cfg(unix)]
+    "sock",
+    "log", "logs",
+    #[cfg(all(unix, not(target_os = "android")))]
+    "trash",
+    #[cfg(unix)]
+    "xdg-config-home",
+    #[cfg(unix)]
+    "xdg_config_home",
+    #[cfg(unix)]
+    "xdg-data-home",
+    #[cfg(unix)]
+    "xdg_data_home",
+    #[cfg(unix)]
+    "xdg-cache-home",
+    #[cfg(unix)]
+    "xdg_cache_home",
+    #[cfg(unix)]
+    "xdg-state-home",
+    #[cfg(unix)]
+    "xdg_state_home",
+    #[cfg(unix)]
+    "xdg-runtime-dir",
+    #[cfg(unix)]
+    "xdg_runtime_dir",
+    "cargo-home", "cargo_home",
+    "rustup-home", "rustup_home",
+    "cwd", "current", "pwd",
+    "self-exe", "self_exe", "current-exe", "current_exe",
+    "self-exe-dir", "self_exe_dir", "current-exe-dir", "current_exe_dir",
+    "scratch",
+    "template",
+    "video", "movie",
+    "tmp",
+    #[cfg(feature = "rand")]
+    "tmp-rand",
+    #[cfg(feature = "rand")]
+    "tmp_random",
+    "temp", "temporary",
+    #[cfg(target_os = "android")]
+    "sd",
+    #[cfg(windows)]
+    "local-low",
+    #[cfg(windows)]
+    "local_low",
+    #[cfg(windows)]
+    "appdata-roaming",
+    #[cfg(windows)]
+    "appdata_roaming",
+    #[cfg(windows)]
+    "appdata-local",
+    #[cfg(windows)]
+    "appdata_local",
+    "cli-data", "cli_data",
+    "cli-cfg", "cli_cfg", "cli_config",
+    "cli-cache", "cli_cache",
+    #[cfg(target_os = "linux")]
+    "thumbnails",
+    #[cfg(target_os = "linux")]
+    "thumbnail",
+    #[cfg(windows)]
+    "progam-files",
+    #[cfg(windows)]
+    "program_files",
+    #[cfg(windows)]
+    "program-files-x86",
+    #[cfg(windows)]
+    "program_files_x86",
+    #[cfg(windows)]
+    "common-program-files",
+    #[cfg(windows)]
+    "common_program_files",
+    #[cfg(windows)]
+    "common-program-files-x86",
+    #[cfg(windows)]
+    "common_program_files_x86",
+    #[cfg(windows)]
+    "program-data",
+    #[cfg(windows)]
+    "program_data",
+    #[cfg(windows)]
+    "microsoft",
+    #[cfg(any(unix, windows))]
+    "root",
+    "empty",
+];
+
+/// A backend for the tests. The directories in the map are overridden (`None` means unavailable), the others come from `dirs`.
+#[cfg(test)]
+#[derive(Default)]
+pub(crate) struct MockDirs(std::collections::HashMap<&'static str, Option<PathBuf>>);
+
+#[cfg(test)]
+impl MockDirs {
+    /// Overrides the directory of `name` (e.g. `cache` for `cache_dir()`).
+    pub(crate) fn with(mut self, name: &'static str, dir: Option<&str>) -> Self {
+        self.0.insert(name, dir.map(PathBuf::from));
+        self
+    }
+
+    fn get(&self, name: &str, f: fn() -> Option<PathBuf>) -> Option<PathBuf> {
+        match self.0.get(name) {
+            Some(dir) => dir.clone(),
+            None => f(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl DirsBackend for MockDirs {
+    fn home_dir(&self) -> Option<PathBuf> {
+        self.get("home", dirs::home_dir)
+    }
+    fn cache_dir(&self) -> Option<PathBuf> {
+        self.get("cache", dirs::cache_dir)
+    }
+    fn config_dir(&self) -> Option<PathBuf> {
+        self.get("config", dirs::config_dir)
+    }
+    fn config_local_dir(&self) -> Option<PathBuf> {
+        self.get("config_local", dirs::config_local_dir)
+    }
+    fn data_dir(&self) -> Option<PathBuf> {
+        self.get("data", dirs::data_dir)
+    }
+    fn data_local_dir(&self) -> Option<PathBuf> {
+        self.get("data_local", dirs::data_local_dir)
+    }
+    fn preference_dir(&self) -> Option<PathBuf> {
+        self.get("preference", dirs::preference_dir)
+    }
+    fn runtime_dir(&self) -> Option<PathBuf> {
+        self.get("runtime", dirs::runtime_dir)
+    }
+    fn state_dir(&self) -> Option<PathBuf> {
+        self.get("state", dirs::state_dir)
+    }
+    fn audio_dir(&self) -> Option<PathBuf> {
+        self.get("audio", dirs::audio_dir)
+    }
+    fn desktop_dir(&self) -> Option<PathBuf> {
+        self.get("desktop", dirs::desktop_dir)
+    }
+    fn document_dir(&self) -> Option<PathBuf> {
+        self.get("document", dirs::document_dir)
+    }
+    fn download_dir(&self) -> Option<PathBuf> {
+        self.get("download", dirs::download_dir)
+    }
+    fn executable_dir(&self) -> Option<PathBuf> {
+        self.get("executable", dirs::executable_dir)
+    }
+    fn font_dir(&self) -> Option<PathBuf> {
+        self.get("font", dirs::font_dir)
+    }
+    fn picture_dir(&self) -> Option<PathBuf> {
+        self.get("picture", dirs::picture_dir)
+    }
+    fn public_dir(&self) -> Option<PathBuf> {
+        self.get("public", dirs::public_dir)
+    }
+    fn template_dir(&self) -> Option<PathBuf> {
+        self.get("template", dirs::template_dir)
+    }
+    fn video_dir(&self) -> Option<PathBuf> {
+        self.get("video", dirs::video_dir)
+    }
+}
+
+/// Consults the environment variables (e.g. `XDG_CONFIG_HOME`) directly before falling back to the inner backend (see [ParseOptions::env_first()](crate::ParseOptions::env_first)).
+pub(crate) struct EnvFirstDirs<'b> {
+    pub(crate) inner: &'b dyn DirsBackend,
+    /// Gets the value of a variable, i.e. the process environment, or the map of `de_with_env()`.
+    pub(crate) env: &'b dyn Fn(&str) -> Option<OsString>,
+}
+
+impl EnvFirstDirs<'_> {
+    /// Returns the value of `var` if it is an absolute path. A relative path is ignored, as the XDG spec requires.
+    fn env_dir(&self, var: &str) -> Option<PathBuf> {
+        (self.env)(var)
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+    }
+
+    /// Consults the XDG variable, and then the Windows variable (e.g. `APPDATA`) on Windows.
+    fn xdg_or_win(&self, xdg: &str, _win: Option<&str>) -> Option<PathBuf> {
+        let dir = self.env_dir(xdg);
+        #[cfg(windows)]
+        let dir = dir.or_else(|| _win.and_then(|x| self.env_dir(x)));
+        dir
+    }
+}
+
+impl DirsBackend for EnvFirstDirs<'_> {
+    fn home_dir(&self) -> Option<PathBuf> {
+        self.env_dir(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+            .or_else(|| self.inner.home_dir())
+    }
+    fn cache_dir(&self) -> Option<PathBuf> {
+        self.xdg_or_win("XDG_CACHE_HOME", Some("LOCALAPPDATA"))
+            .or_else(|| self.inner.cache_dir())
+    }
+    fn config_dir(&self) -> Option<PathBuf> {
+        self.xdg_or_win("XDG_CONFIG_HOME", Some("APPDATA"))
+            .or_else(|| self.inner.config_dir())
+    }
+    fn config_local_dir(&self) -> Option<PathBuf> {
+        self.xdg_or_win("XDG_CONFIG_HOME", Some("LOCALAPPDATA"))
+            .or_else(|| self.inner.config_local_dir())
+    }
+    fn data_dir(&self) -> Option<PathBuf> {
+        self.xdg_or_win("XDG_DATA_HOME", Some("APPDATA"))
+            .or_else(|| self.inner.data_dir())
+    }
+    fn data_local_dir(&self) -> Option<PathBuf> {
+        self.xdg_or_win("XDG_DATA_HOME", Some("LOCALAPPDATA"))
+            .or_else(|| self.inner.data_local_dir())
+    }
+    fn preference_dir(&self) -> Option<PathBuf> {
+        self.xdg_or_win("XDG_CONFIG_HOME", Some("APPDATA"))
+            .or_else(|| self.inner.preference_dir())
+    }
+    fn runtime_dir(&self) -> Option<PathBuf> {
+        self.xdg_or_win("XDG_RUNTIME_DIR", None)
+            .or_else(|| self.inner.runtime_dir())
+    }
+    fn state_dir(&self) -> Option<PathBuf> {
+        self.xdg_or_win("XDG_STATE_HOME", None)
+            .or_else(|| self.inner.state_dir())
+    }
+    fn audio_dir(&self) -> Option<PathBuf> {
+        self.inner.audio_dir()
+    }
+    fn desktop_dir(&self) -> Option<PathBuf> {
+        self.inner.desktop_dir()
+    }
+    fn document_dir(&self) -> Option<PathBuf> {
+        self.inner.document_dir()
+    }
+    fn download_dir(&self) -> Option<PathBuf> {
+        self.inner.download_dir()
+    }
+    fn executable_dir(&self) -> Option<PathBuf> {
+        self.inner.executable_dir()
+    }
+    fn font_dir(&self) -> Option<PathBuf> {
+        self.inner.font_dir()
+    }
+    fn picture_dir(&self) -> Option<PathBuf> {
+        self.inner.picture_dir()
+    }
+    fn public_dir(&self) -> Option<PathBuf> {
+        self.inner.public_dir()
+    }
+    fn template_dir(&self) -> Option<PathBuf> {
+        self.inner.template_dir()
+    }
+    fn video_dir(&self) -> Option<PathBuf> {
+        self.inner.video_dir()
+    }
+    #[cfg(windows)]
+    fn saved_games_dir(&self) -> Option<PathBuf> {
+        self.inner.saved_games_dir()
+    }
+}
+
 /// Returns the path to the temporary directory, either specified by the `TMPDIR` environment variable or the system temporary directory.
 pub fn get_tmp_dir() -> PathBuf {
     match env::var_os("TMPDIR") {
@@ -213,6 +829,39 @@ pub fn get_tmp_dir() -> PathBuf {
     }
 }
 
+/// Returns the file stem of the current executable, which is used as the `[pkg]` of `$dir: log` and `$dir: scratch`.
+fn get_exe_stem() -> Option<OsString> {
+    env::current_exe()
+        .ok()?
+        .file_stem()
+        .map(OsStr::to_os_string)
+}
+
+/// Returns the per-process scratch directory (`$dir: scratch`), i.e. `[runtime-or-tmp]/[pkg]-[pid]`.
+///
+/// It is intended for short-lived files (e.g. clipboard or drag-and-drop), and each process gets an isolated directory.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::dirs::get_scratch_dir;
+///
+/// let dir = get_scratch_dir(std::process::id());
+/// dbg!(&dir);
+/// ```
+pub fn get_scratch_dir(pid: u32) -> Option<PathBuf> {
+    join_scratch_dir(dirs::runtime_dir(), pid)
+}
+
+/// Joins `[pkg]-[pid]` to the runtime dir, or to the tmp dir if `runtime` is None.
+fn join_scratch_dir(runtime: Option<PathBuf>, pid: u32) -> Option<PathBuf> {
+    let mut name = get_exe_stem()?;
+    name.push(format!("-{pid}"));
+
+    let base = runtime.unwrap_or_else(get_tmp_dir);
+    Some(base.join(name))
+}
+
 /// Generates a random temporary directory.(`rand` feature needs to be enabled)
 ///
 /// # OverView
@@ -252,9 +901,15 @@ pub fn get_tmp_random_dir(
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "rand")]
     use super::*;
     use crate::EnvPath;
 
+    /// Matches `ident` with the real base directories.
+    fn system_dirs(ident: &str) -> crate::OsCow<'_> {
+        EnvPath::match_base_dirs_with(ident, &super::SystemDirs, &super::process_env)
+    }
+
     // #[test]
     // fn strange_dir() {
     //     let mut path =
@@ -275,6 +930,446 @@ mod tests {
         dbg!(p2);
     }
 
+    #[test]
+    #[cfg(unix)]
+    fn socket_dir() {
+        let path = EnvPath::new(["$dir: socket"]);
+        let expected = dirs::runtime_dir().unwrap_or_else(|| "/tmp".into());
+        assert_eq!(path.path, Some(expected));
+
+        assert_eq!(EnvPath::new(["$dir: sock"]).path, path.path);
+    }
+
+    #[test]
+    fn xdg_dirs() {
+        use std::{env, path::PathBuf};
+
+        let expected = |var: &str, default: Option<&str>| match () {
+            #[cfg(unix)]
+            () => env::var_os(var)
+                .map(PathBuf::from)
+                .filter(|p| p.is_absolute())
+                .or_else(|| dirs::home_dir().zip(default).map(|(h, d)| h.join(d))),
+            #[cfg(not(unix))]
+            () => {
+                let _ = (var, default);
+                None
+            }
+        };
+
+        for (ident, var, default) in [
+            ("xdg-config-home", "XDG_CONFIG_HOME", Some(".config")),
+            ("xdg_data_home", "XDG_DATA_HOME", Some(".local/share")),
+            ("xdg-cache-home", "XDG_CACHE_HOME", Some(".cache")),
+            ("xdg-state-home", "XDG_STATE_HOME", Some(".local/state")),
+            ("xdg-runtime-dir", "XDG_RUNTIME_DIR", None),
+        ] {
+            let p = EnvPath::parse_str(&format!("$dir: {ident} ?? empty"));
+            let e = expected(var, default)
+                .filter(|p| p.exists())
+                .unwrap_or_default();
+            assert_eq!(p, Some(e), "{ident}");
+        }
+    }
+
+    #[test]
+    fn env_first_base_dirs() {
+        use crate::{parser::ParseCtx, ParseOptions};
+        use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+
+        let opts = ParseOptions::new().env_first(true);
+        let de = |map: &HashMap<String, OsString>, env_first: bool| {
+            let ctx = ParseCtx {
+                env_map: Some(map),
+                options: Some(&opts).filter(|_| env_first),
+                ..Default::default()
+            };
+            EnvPath::handle_dirs("cfg", ctx).map(|x| PathBuf::from(x.into_owned()))
+        };
+
+        let cfg = if cfg!(windows) { r"C:\srv\cfg" } else { "/srv/cfg" };
+        let mut map = HashMap::from([("XDG_CONFIG_HOME".to_owned(), OsString::from(cfg))]);
+
+        assert_eq!(de(&map, true), Some(cfg.into()));
+        // Off by default.
+        assert_eq!(de(&map, false), dirs::config_dir());
+
+        // A relative path is ignored.
+        map.insert("XDG_CONFIG_HOME".into(), "srv/cfg".into());
+        assert_eq!(de(&map, true), dirs::config_dir());
+
+        map.remove("XDG_CONFIG_HOME");
+        assert_eq!(de(&map, true), dirs::config_dir());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn env_idents_of_map() {
+        use crate::ParseOptions;
+        use std::path::PathBuf;
+
+        let opts = ParseOptions::new()
+            .env_first(true)
+            .env_map([
+                ("XDG_CONFIG_HOME", "/map/cfg"),
+                ("HOME", "/map/home"),
+                ("CARGO_HOME", "/map/cargo"),
+                ("PATH", "/map/bin:/map/sbin"),
+            ]);
+        let de = |ident: &str| {
+            EnvPath::from([format!("$dir: {ident}").as_str()])
+                .de_with_options(&opts)
+                .path
+        };
+
+        for (ident, expected) in [
+            ("xdg-config-home", "/map/cfg"),
+            ("xdg-data-home", "/map/home/.local/share"),
+            ("cargo-home", "/map/cargo"),
+            ("rustup-home", "/map/home/.rustup"),
+            ("first-path", "/map/bin"),
+            ("last-path", "/map/sbin"),
+        ] {
+            assert_eq!(de(ident), Some(PathBuf::from(expected)), "{ident}");
+        }
+
+        // `XDG_RUNTIME_DIR` has no default, so it is not read from the process env either.
+        assert_eq!(de("xdg-runtime-dir"), Some(PathBuf::from("$dir: xdg-runtime-dir")));
+    }
+
+    #[test]
+    fn env_first_falls_back_to_inner() {
+        use super::{DirsBackend, EnvFirstDirs, MockDirs};
+        use std::ffi::OsString;
+
+        let mock = MockDirs::default()
+            .with("state", Some("/mock/state"))
+            .with("desktop", Some("/mock/desktop"));
+
+        let env = |var: &str| (var == "HOME").then(|| OsString::from("/env/home"));
+        let b = EnvFirstDirs {
+            inner: &mock,
+            env: &env,
+        };
+
+        #[cfg(not(windows))]
+        assert_eq!(b.home_dir(), Some("/env/home".into()));
+        assert_eq!(b.state_dir(), Some("/mock/state".into()));
+        assert_eq!(b.desktop_dir(), Some("/mock/desktop".into()));
+        assert_eq!(b.cache_dir(), dirs::cache_dir());
+    }
+
+    #[test]
+    fn downloads_is_dl() {
+        assert_eq!(
+            system_dirs("downloads"),
+            system_dirs("dl")
+        );
+        #[cfg(not(windows))]
+        assert_eq!(system_dirs("saved-games"), None);
+    }
+
+    #[test]
+    fn incoming_and_outgoing() {
+        use std::path::PathBuf;
+
+        let dl = system_dirs("dl").map(|x| PathBuf::from(x.into_owned()));
+
+        for (ident, suffix) in [("incoming", "Incoming"), ("outgoing", "Outgoing")] {
+            let dir = system_dirs(ident).map(|x| PathBuf::from(x.into_owned()));
+            assert_eq!(dir, dl.as_ref().map(|x| x.join(suffix)));
+
+            if let Some(d) = dir {
+                assert!(d.ends_with(suffix));
+            }
+        }
+    }
+
+    #[test]
+    fn mock_dirs_backend() {
+        use super::MockDirs;
+        use std::path::Path;
+
+        let mock = MockDirs::default()
+            .with("cache", Some("/mock/cache"))
+            .with("config", Some("/mock/config"))
+            .with("data", None);
+        let de = |ident| EnvPath::match_base_dirs_with(ident, &mock, &super::process_env);
+
+        assert_eq!(de("cache").as_deref(), Some(Path::new("/mock/cache").as_os_str()));
+        assert_eq!(de("cli-cache"), de("cache"));
+        assert_eq!(de("cfg").as_deref(), Some(Path::new("/mock/config").as_os_str()));
+        assert_eq!(de("config"), de("cfg"));
+        assert_eq!(de("data"), None);
+
+        // The directories that are not overridden come from `dirs`.
+        assert_eq!(de("home"), system_dirs("home"));
+    }
+
+    #[test]
+    fn known_dirs() {
+        for ident in ["cfg", "public", "desktop", "cwd", "log", "template", "tmp"] {
+            assert!(EnvPath::is_known_dir(ident), "{ident}");
+        }
+        for ident in ["none_such", "env * home", ""] {
+            assert!(!EnvPath::is_known_dir(ident), "{ident}");
+        }
+        #[cfg(unix)]
+        assert!(EnvPath::is_known_dir("xdg-runtime-dir"));
+        #[cfg(not(windows))]
+        assert!(!EnvPath::is_known_dir("saved-games"));
+    }
+
+    #[test]
+    fn user_dirs_via_backend() {
+        use super::MockDirs;
+        use std::path::{Path, PathBuf};
+
+        let mock = MockDirs::default()
+            .with("audio", Some("/mock/music"))
+            .with("desktop", Some("/mock/desktop"))
+            .with("document", Some("/mock/doc"))
+            .with("download", Some("/mock/dl"))
+            .with("picture", Some("/mock/pic"))
+            .with("video", Some("/mock/video"))
+            .with("runtime", Some("/mock/run"))
+            .with("state", Some("/mock/state"))
+            .with("data_local", Some("/mock/local"))
+            .with("home", Some("/mock/home"));
+        let de = |ident| {
+            EnvPath::match_base_dirs_with(ident, &mock, &super::process_env).map(|x| PathBuf::from(x.into_owned()))
+        };
+
+        #[cfg(not(target_os = "android"))]
+        for (ident, expected) in [
+            ("music", "/mock/music"),
+            ("desktop", "/mock/desktop"),
+            ("doc", "/mock/doc"),
+            ("dl", "/mock/dl"),
+            ("downloads", "/mock/dl"),
+            ("pic", "/mock/pic"),
+            ("video", "/mock/video"),
+        ] {
+            assert_eq!(de(ident).as_deref(), Some(Path::new(expected)), "{ident}");
+        }
+        assert_eq!(de("incoming"), de("dl").map(|x| x.join("Incoming")));
+
+        let scratch = de("scratch").expect("Failed to get the scratch dir");
+        assert!(scratch.starts_with("/mock/run"));
+
+        let log = de("log").expect("Failed to get the log dir");
+        #[cfg(not(any(target_os = "macos", windows)))]
+        assert!(log.starts_with("/mock/state"));
+        #[cfg(target_os = "macos")]
+        assert!(log.starts_with("/mock/home/Library/Logs"));
+        #[cfg(windows)]
+        assert!(log.starts_with("/mock/local"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn thumbnails_dir() {
+        use super::MockDirs;
+        use std::path::Path;
+
+        let de = |cache| {
+            let mock = MockDirs::default().with("cache", cache);
+            EnvPath::match_base_dirs_with("thumbnails", &mock, &super::process_env).map(|x| x.into_owned())
+        };
+
+        assert_eq!(
+            de(Some("/mock/cache")).as_deref(),
+            Some(Path::new("/mock/cache/thumbnails").as_os_str())
+        );
+        assert_eq!(de(None), None);
+
+        let path = EnvPath::new(["$dir: thumbnail"]).path;
+        assert_eq!(path, dirs::cache_dir().map(|x| x.join("thumbnails")));
+    }
+
+    #[test]
+    fn trash_dir() {
+        use super::MockDirs;
+        use std::path::PathBuf;
+
+        let mock = MockDirs::default()
+            .with("home", Some("/mock/home"))
+            .with("data", Some("/mock/home/.local/share"));
+
+        let trash = EnvPath::match_base_dirs_with("trash", &mock, &super::process_env)
+            .map(|x| PathBuf::from(x.into_owned()));
+
+        #[cfg(all(unix, not(any(target_os = "macos", target_os = "android"))))]
+        assert_eq!(trash, Some("/mock/home/.local/share/Trash".into()));
+
+        #[cfg(target_os = "macos")]
+        assert_eq!(trash, Some("/mock/home/.Trash".into()));
+
+        #[cfg(any(not(unix), target_os = "android"))]
+        assert_eq!(trash, None);
+    }
+
+    #[test]
+    fn appdata_dirs() {
+        use std::path::PathBuf;
+
+        let de = |ident| system_dirs(ident).map(|x| PathBuf::from(x.into_owned()));
+
+        #[cfg(windows)]
+        {
+            assert_eq!(de("appdata-roaming"), dirs::data_dir());
+            assert_eq!(de("appdata_local"), dirs::data_local_dir());
+            assert!(de("appdata-roaming").is_some_and(|x| x.ends_with("Roaming")));
+            assert!(de("appdata-local").is_some_and(|x| x.ends_with("Local")));
+        }
+
+        #[cfg(not(windows))]
+        for ident in ["appdata-roaming", "appdata-local"] {
+            assert_eq!(de(ident), None);
+        }
+    }
+
+    #[test]
+    fn trash_dir_fallback() {
+        let p = EnvPath::new(["$dir: trash ? tmp"]);
+
+        #[cfg(any(not(unix), target_os = "android"))]
+        assert_eq!(p.path, EnvPath::new(["$dir: tmp"]).path);
+
+        #[cfg(all(unix, not(target_os = "android")))]
+        assert_eq!(p.path, EnvPath::new(["$dir: trash"]).path);
+    }
+
+    #[test]
+    fn cargo_and_rustup_home() {
+        use crate::ParseOptions;
+        use std::env;
+
+        for (ident, var) in [("cargo-home", "CARGO_HOME"), ("rustup_home", "RUSTUP_HOME")] {
+            let custom = env::temp_dir().join(format!("envpath_{ident}"));
+            let opts = ParseOptions::new().env_map([(var, custom.clone())]);
+
+            let raw = format!("$dir: {ident}");
+            let path = EnvPath::from([raw.as_str()]).de_with_options(&opts);
+            assert_eq!(path.path, Some(custom));
+        }
+    }
+
+    #[test]
+    fn tool_home_fallback() {
+        let home = dirs::home_dir().unwrap();
+        let p = EnvPath::set_tool_home(
+            "ENVPATH_TEST_TOOL_HOME_UNSET",
+            ".cargo",
+            &super::SystemDirs,
+            &super::process_env,
+        );
+        assert_eq!(p.as_deref(), Some(home.join(".cargo").as_os_str()));
+    }
+
+    /// With the `winfolder` feature, `desktop` and `doc` are obtained via `SHGetKnownFolderPath`, so a Desktop (or Documents) redirected into OneDrive is honored.
+    ///
+    /// It is ignored by default, as it requires the folders of the current user to be redirected into OneDrive.
+    #[test]
+    #[cfg(all(windows, feature = "winfolder"))]
+    #[ignore]
+    fn onedrive_redirected_folders() {
+        let onedrive = std::env::var_os("OneDrive").expect("OneDrive is not set up");
+
+        let desktop = EnvPath::new(["$dir: desktop"]);
+        let doc = EnvPath::new(["$dir: doc"]);
+        assert_eq!(desktop.path, crate::winfolder::desktop_dir());
+        assert_eq!(doc.path, crate::winfolder::documents_dir());
+
+        assert!(desktop.starts_with(&onedrive));
+        assert!(doc.starts_with(&onedrive));
+    }
+
+    #[test]
+    fn root_dir() {
+        let root = EnvPath::new(["$dir: root", "etc"]);
+
+        #[cfg(unix)]
+        assert_eq!(root.to_str(), Some("/etc"));
+
+        #[cfg(windows)]
+        {
+            let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".into());
+            assert_eq!(root.to_str(), Some(format!(r#"{drive}\etc"#).as_str()));
+            assert!(root.is_absolute());
+        }
+    }
+
+    #[test]
+    fn scratch_dir_per_process() {
+        use super::get_scratch_dir;
+
+        let (a, b) = (get_scratch_dir(1), get_scratch_dir(2));
+        assert_ne!(a, b);
+
+        let path = EnvPath::new(["$dir: scratch"]).path;
+        assert_eq!(path, get_scratch_dir(std::process::id()));
+
+        let name = path
+            .as_deref()
+            .and_then(|x| x.file_name())
+            .map(|x| x.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        assert!(name.ends_with(&format!("-{}", std::process::id())));
+    }
+
+    #[test]
+    fn current_dir() {
+        let cwd = std::env::current_dir().unwrap();
+
+        for ident in ["cwd", "current", "pwd"] {
+            let raw = format!("$dir: {ident}");
+            let p = EnvPath::new([raw.as_str(), "a"]);
+            assert_eq!(p.path, Some(cwd.join("a")));
+        }
+
+        let p = EnvPath::new(["$dir: none_such ? cwd"]);
+        assert_eq!(p.path, Some(cwd));
+    }
+
+    #[test]
+    fn current_exe() {
+        let exe = std::env::current_exe().unwrap();
+
+        for ident in ["self-exe", "current-exe"] {
+            let raw = format!("$dir: {ident}");
+            assert_eq!(EnvPath::new([raw.as_str()]).path.as_ref(), Some(&exe));
+        }
+
+        for ident in ["self-exe-dir", "current-exe-dir", "current_exe_dir"] {
+            let raw = format!("$dir: {ident}");
+            assert_eq!(EnvPath::new([raw.as_str()]).path.as_deref(), exe.parent());
+        }
+
+        // `exe` is still the executables directory.
+        assert_ne!(EnvPath::new(["$dir: exe"]).path, Some(exe));
+    }
+
+    #[test]
+    fn log_dir() {
+        let Some(path) = EnvPath::new(["$dir: log"]).path else {
+            return;
+        };
+        let exe = std::env::current_exe().unwrap();
+        let pkg = exe.file_stem().unwrap();
+
+        #[cfg(target_os = "macos")]
+        assert_eq!(path.file_name(), Some(pkg));
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            assert!(path.ends_with("logs"));
+            assert_eq!(path.parent().and_then(|x| x.file_name()), Some(pkg));
+        }
+
+        assert_eq!(EnvPath::new(["$dir: logs"]).path, Some(path));
+    }
+
     #[test]
     #[cfg(feature = "rand")]
     fn random_tmp_dir() {
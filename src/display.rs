@@ -0,0 +1,207 @@
+use crate::{EnvPath, Raw};
+use std::fmt;
+
+impl fmt::Display for EnvPath<'_> {
+    /// Shows the resolved path (via `Path::display`), or an empty string if it has not been resolved, so `println!("{}", path)` works without calling `.display()`.
+    ///
+    /// With the alternate flag (`{:#}`), an unresolved path shows its raw chunks instead (e.g. `$env: home / dev`), which tells "resolved to empty" from "never resolved" in logs and error messages. A resolved path is shown the same as `{}`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["dev"]).de();
+    /// assert_eq!(path.to_string(), "dev");
+    ///
+    /// let raw = EnvPath::from(["$env: home", "dev"]);
+    /// assert_eq!(raw.to_string(), "");
+    /// assert_eq!(format!("{raw:#}"), "$env: home / dev");
+    ///
+    /// // No need to call `.display()`.
+    /// let tmp = EnvPath::new(["$dir: tmp"]);
+    /// assert!(!format!("{}", tmp).is_empty());
+    /// println!("{tmp}");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.path {
+            None if f.alternate() => f.pad(&self.raw.to_string()),
+            _ => f.pad(&self.to_string_or("")),
+        }
+    }
+}
+
+impl fmt::Display for Raw<'_> {
+    /// Joins the raw chunks with ` / `, e.g. `$env: home / dev`, which is more readable than `Debug` in logs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$env: home", "dev"]);
+    /// assert_eq!(path.get_raw().to_string(), "$env: home / dev");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, chunk) in self.iter().enumerate() {
+            if i != 0 {
+                f.write_str(" / ")?;
+            }
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+impl EnvPath<'_> {
+    /// Returns the resolved path as a (lossy) UTF-8 `String`, or `default` if it has not been resolved.
+    ///
+    /// This is useful for APIs that require a `String`, e.g. building a URL or a command-line argument.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["dev"]);
+    /// assert_eq!(path.to_string_or("/tmp"), "dev");
+    ///
+    /// let raw = EnvPath::from(["$env: home"]);
+    /// assert_eq!(raw.to_string_or("/tmp"), "/tmp");
+    /// ```
+    pub fn to_string_or(&self, default: &str) -> String {
+        match &self.path {
+            Some(p) => p.to_string_lossy().into_owned(),
+            None => default.to_owned(),
+        }
+    }
+
+    /// Similar to `to_string()`, but on Windows, the verbatim prefix of the resolved path is stripped for human-readable output.
+    ///
+    /// - `\\?\C:\Users\m` => `C:\Users\m`
+    /// - `\\?\UNC\server\share` => `\\server\share`
+    ///
+    /// The stored `path` keeps the prefix, so use it (rather than this string) for the actual I/O.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["dev"]);
+    /// assert_eq!(path.display_clean(), "dev");
+    /// ```
+    pub fn display_clean(&self) -> String {
+        let s = self.to_string();
+
+        match self.path {
+            Some(_) if cfg!(windows) => strip_verbatim(&s).unwrap_or(s),
+            _ => s,
+        }
+    }
+}
+
+/// Strips the verbatim prefix (`\\?\` or `\\?\UNC\`) of a Windows path.
+fn strip_verbatim(s: &str) -> Option<String> {
+    match s.strip_prefix(r"\\?\UNC\") {
+        Some(x) => Some(format!(r"\\{x}")),
+        _ => s.strip_prefix(r"\\?\").map(str::to_owned),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::EnvPath;
+
+    #[test]
+    fn display_resolved_and_unresolved() {
+        let raw = EnvPath::from(["$env: home", "dev"]);
+        assert_eq!(raw.to_string(), "");
+        assert_eq!(raw.display().to_string(), "");
+
+        let path = raw.de();
+        assert_eq!(
+            path.to_string(),
+            path.path
+                .as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default()
+        );
+
+        assert_eq!(EnvPath::default().to_string(), "");
+        assert_eq!(format!("{:>4}", EnvPath::new(["a"])), "   a");
+    }
+
+    #[test]
+    fn display_alternate_shows_raw() {
+        let raw = EnvPath::from(["$env: home", "dev"]);
+        assert_eq!(format!("{raw}"), "");
+        assert_eq!(format!("{raw:#}"), "$env: home / dev");
+        assert_eq!(format!("{raw:#}"), raw.get_raw().to_string());
+
+        // A resolved path is the same as `{}`, even if it is empty.
+        let path = EnvPath::new(["a", "b"]);
+        assert_eq!(format!("{path:#}"), format!("{path}"));
+        let empty = EnvPath::new([""]);
+        assert_eq!(empty.path.as_deref(), Some(std::path::Path::new("")));
+        assert_eq!(format!("{empty:#}"), "");
+
+        assert_eq!(format!("{:#}", EnvPath::default()), "");
+    }
+
+    #[test]
+    fn display_raw_of_every_variant() {
+        use crate::Raw;
+        use std::borrow::Cow;
+
+        let expected = "$env: home / dev";
+        assert_eq!(Raw::Ref(vec!["$env: home", "dev"]).to_string(), expected);
+        assert_eq!(
+            Raw::Cow(vec![Cow::from("$env: home"), Cow::from("dev")]).to_string(),
+            expected
+        );
+        assert_eq!(
+            Raw::Owned(vec!["$env: home".into(), "dev".into()]).to_string(),
+            expected
+        );
+
+        assert_eq!(Raw::Ref(vec!["a"]).to_string(), "a");
+        assert_eq!(Raw::default().to_string(), "");
+    }
+
+    #[test]
+    fn string_or_default() {
+        let path = EnvPath::new(["a", "b"]);
+        assert_eq!(
+            path.to_string_or("x"),
+            std::path::Path::new("a").join("b").to_string_lossy()
+        );
+
+        assert_eq!(EnvPath::from(["a"]).to_string_or("x"), "x");
+        assert_eq!(EnvPath::default().to_string_or(""), "");
+    }
+
+    #[test]
+    fn strip_verbatim_prefix() {
+        use super::strip_verbatim;
+
+        assert_eq!(strip_verbatim(r"\\?\C:\Users\m").as_deref(), Some(r"C:\Users\m"));
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\server\share").as_deref(),
+            Some(r"\\server\share")
+        );
+        assert_eq!(strip_verbatim(r"C:\Users\m"), None);
+        assert_eq!(strip_verbatim(r"\\server\share"), None);
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn display_clean_verbatim() {
+        let path = EnvPath::new([r"\\?\C:\Users\m", "dev"]);
+        assert!(path.to_string().starts_with(r"\\?\"));
+        assert_eq!(path.display_clean(), r"C:\Users\m\dev");
+
+        let unc = EnvPath::new([r"\\?\UNC\server\share", "dev"]);
+        assert_eq!(unc.display_clean(), r"\\server\share\dev");
+    }
+}
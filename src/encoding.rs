@@ -0,0 +1,50 @@
+//! The RFC 4648 encodings, which are shared by `$val: base64url(NAME)` and the random values (e.g. `$val: rand-base32-8`).
+
+/// The alphabet of base64url (RFC 4648, URL and filename safe).
+pub(crate) const BASE64URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes `bytes` with `alphabet` (each char holds `bits` bits), without padding.
+///
+/// The remaining bits of the last char are filled with zeros, e.g. base64url of `hi` is `aGk`.
+pub(crate) fn encode(bytes: &[u8], alphabet: &[u8], bits: usize) -> String {
+    let mask = (1 << bits) - 1;
+    let (mut buf, mut buf_bits) = (0u32, 0);
+
+    let mut out = String::with_capacity((bytes.len() * 8).div_ceil(bits));
+    for &b in bytes {
+        buf = (buf << 8) | u32::from(b);
+        buf_bits += 8;
+        while buf_bits >= bits {
+            buf_bits -= bits;
+            out.push(char::from(alphabet[(buf >> buf_bits) as usize & mask]));
+        }
+        buf &= (1 << buf_bits) - 1;
+    }
+
+    if buf_bits > 0 {
+        out.push(char::from(alphabet[(buf << (bits - buf_bits)) as usize & mask]));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc4648_vectors() {
+        let base32 = |s: &str| encode(s.as_bytes(), b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567", 5);
+        let base64url = |s: &[u8]| encode(s, BASE64URL_ALPHABET, 6);
+
+        assert_eq!(base32(""), "");
+        assert_eq!(base32("f"), "MY");
+        assert_eq!(base32("foobar"), "MZXW6YTBOI");
+
+        assert_eq!(base64url(b""), "");
+        assert_eq!(base64url(b"hi"), "aGk");
+        assert_eq!(base64url(b"a/b?"), "YS9iPw");
+        assert_eq!(base64url(b"\xfb\xff\xbf"), "-_-_");
+        assert_eq!(base64url(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,62 @@
+use std::{error::Error, fmt, path::PathBuf};
+
+/// The error of [EnvPath::try_de()](crate::EnvPath::try_de), [EnvPath::try_de_with_options()](crate::EnvPath::try_de_with_options) [EnvPath::try_resolve_one()](crate::EnvPath::try_resolve_one) and the strict `TryFrom` conversions.
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ParseError {
+    /// The raw sequence is empty, so there is nothing to resolve.
+    Unresolved,
+    /// [ParseOptions::require_absolute()](crate::ParseOptions::require_absolute) is set, but the path is relative (or unresolved, in which case it is empty).
+    NotAbsolute(PathBuf),
+    /// The scheme of the chunk (e.g. `$foo` in `$foo: bar`) is unknown, or its feature is disabled.
+    UnknownScheme(String),
+    /// The ident of the chunk (e.g. `foo` in `$dir: foo`) is unknown, or it is unavailable on the current platform.
+    UnknownIdent(String),
+    /// The special chunk (e.g. `$env: foo`) is valid, but it cannot be resolved, e.g. the variable is unset. Only the strict conversions (e.g. `EnvPath::try_from(vec)`) report it.
+    UnresolvedChunk(String),
+    /// The `ProjectDirs` of `$proj(...)` exists, but it does not provide the directory of `ident` (e.g. `runtime` if `XDG_RUNTIME_DIR` is unset). Use `?` for a fallback, e.g. `$proj(com.x.y): runtime ? cache`.
+    ProjectDirUnavailable { ident: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unresolved => f.write_str("the path could not be resolved"),
+            Self::NotAbsolute(p) => {
+                write!(f, "the path is not absolute: {:?}", p.display())
+            }
+            Self::UnknownScheme(s) => write!(f, "unknown scheme: {s:?}"),
+            Self::UnknownIdent(s) => write!(f, "unknown ident: {s:?}"),
+            Self::UnresolvedChunk(s) => write!(f, "the chunk could not be resolved: {s:?}"),
+            Self::ProjectDirUnavailable { ident } => {
+                write!(f, "the project directory is unavailable: {ident:?}")
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_message() {
+        assert_eq!(
+            ParseError::NotAbsolute("a/b".into()).to_string(),
+            r#"the path is not absolute: "a/b""#
+        );
+        assert_eq!(
+            ParseError::UnknownScheme("$foo".into()).to_string(),
+            r#"unknown scheme: "$foo""#
+        );
+        assert_eq!(
+            ParseError::ProjectDirUnavailable {
+                ident: "runtime".into()
+            }
+            .to_string(),
+            r#"the project directory is unavailable: "runtime""#
+        );
+    }
+}
@@ -1,6 +1,11 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
 
-use crate::{EnvPath, Raw};
+use crate::{EnvPath, ParseError, Raw};
 
 impl FromIterator<String> for EnvPath<'_> {
     /// This is similar to `new()`.
@@ -102,6 +107,101 @@ impl<'r, T: AsRef<str>> From<&[T]> for EnvPath<'r> {
     }
 }
 
+impl From<PathBuf> for EnvPath<'_> {
+    /// Wraps a concrete path, which is already resolved, so it does not go through the raw parsing.
+    ///
+    /// The raw is a single chunk of the path string, so that `get_raw()` and serialization still work. For a non-UTF-8 path, the raw is lossy, but `path` is kept exactly.
+    ///
+    /// A path that would be read as a special chunk (e.g. `$Recycle.Bin`) is escaped (i.e. `\$Recycle.Bin`), so that it is still the same path after a serde round trip.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    /// use std::path::PathBuf;
+    ///
+    /// let path = EnvPath::from(PathBuf::from("/srv/app"));
+    /// assert_eq!(path.path, Some(PathBuf::from("/srv/app")));
+    /// assert_eq!(path.get_raw().iter().collect::<Vec<_>>(), ["/srv/app"]);
+    ///
+    /// let path = EnvPath::from(PathBuf::from("$env: home"));
+    /// assert_eq!(path.get_raw().iter().collect::<Vec<_>>(), [r"\$env: home"]);
+    /// ```
+    fn from(path: PathBuf) -> Self {
+        let raw = crate::parser::escape_chunk(&path.to_string_lossy()).into_owned();
+
+        Self {
+            raw: Raw::Owned(vec![raw]),
+            path: Some(path),
+        }
+    }
+}
+
+impl From<&Path> for EnvPath<'_> {
+    /// Similar to `From<PathBuf>`.
+    fn from(path: &Path) -> Self {
+        EnvPath::from(path.to_path_buf())
+    }
+}
+
+impl TryFrom<Vec<String>> for EnvPath<'_> {
+    type Error = ParseError;
+
+    /// Unlike `From<Vec<&str>>`, it resolves the path, and returns an error if any special chunk cannot be resolved (e.g. an unset `$env` variable), rather than falling back to the raw str.
+    ///
+    /// For a slice, use [EnvPath::try_from_slice()].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(all(feature = "consts", feature = "dirs"))] {
+    /// use envpath::{EnvPath, ParseError};
+    ///
+    /// let path = EnvPath::try_from(vec!["$const: os".to_owned(), "app".to_owned()]);
+    /// assert!(path.is_ok());
+    ///
+    /// assert_eq!(
+    ///     EnvPath::try_from(vec!["$dir: none_such".to_owned()]),
+    ///     Err(ParseError::UnknownIdent("none_such".into()))
+    /// );
+    /// # }
+    /// ```
+    fn try_from(raw: Vec<String>) -> Result<Self, Self::Error> {
+        Self {
+            raw: Raw::Owned(raw),
+            path: None,
+        }
+        .try_de_strict()
+    }
+}
+
+impl<'r> TryFrom<&'r str> for EnvPath<'r> {
+    type Error = ParseError;
+
+    /// Splits a whole path string on the separators of the OS (`/`, and also `\\` on Windows) into raw chunks, then resolves it in the same (strict) way as `TryFrom<Vec<String>>`.
+    ///
+    /// The leading `$scheme: ident` is one chunk, and the separators inside parentheses (e.g. `$proj(...)`) or after an inline default of `$env` are not split.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "dirs")] {
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::try_from("$dir: cfg/myapp/config.ron").unwrap();
+    /// assert_eq!(path.get_raw().iter().collect::<Vec<_>>(), ["$dir: cfg", "myapp", "config.ron"]);
+    /// assert_eq!(path.path, EnvPath::new(["$dir: cfg", "myapp", "config.ron"]).path);
+    /// # }
+    /// ```
+    fn try_from(raw: &'r str) -> Result<Self, Self::Error> {
+        Self {
+            raw: Self::create_ref_raw(crate::parser::split_path_str(raw)),
+            path: None,
+        }
+        .try_de_strict()
+    }
+}
+
 impl<'r> From<&Vec<&'r str>> for EnvPath<'r> {
     /// # Examples
     ///
@@ -154,6 +254,30 @@ impl<'r> EnvPath<'r> {
         .de()
     }
 
+    /// The strict version of `From<&[T]>`, which resolves the path, and returns an error if any special chunk cannot be resolved (e.g. an unset `$env` variable).
+    ///
+    /// Since `From<&[T]>` is implemented, `TryFrom<&[T]>` is already taken (it never fails), so this is an associated function instead. See also `TryFrom<Vec<String>>`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(all(feature = "consts", feature = "dirs"))] {
+    /// use envpath::{EnvPath, ParseError};
+    ///
+    /// let segments = ["$const: os".to_owned(), "app".to_owned()];
+    /// assert!(EnvPath::try_from_slice(&segments).is_ok());
+    ///
+    /// let segments = ["$dir: none_such"];
+    /// assert_eq!(
+    ///     EnvPath::try_from_slice(&segments),
+    ///     Err(ParseError::UnknownIdent("none_such".into()))
+    /// );
+    /// # }
+    /// ```
+    pub fn try_from_slice<T: AsRef<str>>(raw: &[T]) -> Result<Self, ParseError> {
+        EnvPath::from(raw).try_de_strict()
+    }
+
     /// Create a new instance of `EnvPath` from an iterator over owned strings.
     ///
     /// Note: `new_owned()` will convert `&str` to `String`, which may result in additional heap memory allocation.
@@ -204,7 +328,7 @@ impl<'r> EnvPath<'r> {
     /// # Examples
     ///
     /// ```
-    /// use std::borrow::Cow;
+    /// use std::{borrow::Cow, collections::HashMap, ffi::OsString};
     /// use envpath::EnvPath;
     ///
     /// let arr = [
@@ -229,6 +353,32 @@ impl<'r> EnvPath<'r> {
         .de()
     }
 
+    /// Similar to `new()`, but `$env` reads the environment variables from an in-memory `map`, rather than the process environment.
+    ///
+    /// See also: [de_with_env()](EnvPath::de_with_env)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    /// use std::{collections::HashMap, ffi::OsString, path::Path};
+    ///
+    /// let map = HashMap::from([("XDG_DATA_HOME".to_owned(), OsString::from("/data"))]);
+    /// let path = EnvPath::from_with_env(["$env: xdg-data-home ? home", "app"], &map);
+    ///
+    /// assert_eq!(path.path, Some(Path::new("/data").join("app")));
+    /// ```
+    pub fn from_with_env<V>(iter: V, map: &HashMap<String, OsString>) -> Self
+    where
+        V: IntoIterator<Item = &'r str>,
+    {
+        Self {
+            raw: Self::create_ref_raw(iter),
+            path: None,
+        }
+        .de_with_env(map)
+    }
+
     /// Create a new instance of `Raw` from an iterator over borrowed strings.
     ///
     /// which is used internally by the other constructor methods to create `EnvPath` instances.
@@ -244,6 +394,118 @@ impl<'r> EnvPath<'r> {
 mod tests {
     use crate::EnvPath;
 
+    #[test]
+    fn from_resolved_path() {
+        use std::path::{Path, PathBuf};
+
+        let p = EnvPath::from(Path::new("srv").join("app"));
+        assert_eq!(p.path, Some(Path::new("srv").join("app")));
+        // The raw resolves to the same path.
+        assert_eq!(p.get_raw().parse(), p.path);
+
+        let p = EnvPath::from(Path::new("a"));
+        assert_eq!(p.path, Some(PathBuf::from("a")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn from_non_utf8_path() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::Path};
+
+        let path = Path::new(OsStr::from_bytes(b"/tmp/a\xff"));
+        let p = EnvPath::from(path);
+
+        assert_eq!(p.path.as_deref(), Some(path));
+        assert_eq!(p.get_raw().iter().collect::<Vec<_>>(), ["/tmp/a\u{FFFD}"]);
+    }
+
+    #[test]
+    #[cfg(feature = "dirs")]
+    fn strict_try_from_strings() {
+        use crate::ParseError;
+
+        let ok = vec!["$dir: cfg".to_owned(), "app".to_owned()];
+        let path = EnvPath::try_from_slice(&ok).expect("Failed to resolve");
+        assert_eq!(path.path, EnvPath::from(&ok[..]).de().path);
+        assert!(EnvPath::try_from(ok).is_ok());
+
+        let unset = vec!["$env: envpath_test_unset".to_owned(), "app".to_owned()];
+        assert_eq!(
+            EnvPath::try_from_slice(&unset),
+            Err(ParseError::UnresolvedChunk("$env: envpath_test_unset".into()))
+        );
+        // The lenient `From` falls back to the raw str.
+        assert!(EnvPath::from(&unset[..]).de().path.is_some());
+
+        assert_eq!(
+            EnvPath::try_from(vec!["app".to_owned(), "$foo: bar".to_owned()]),
+            Err(ParseError::UnknownScheme("$foo".into()))
+        );
+        assert_eq!(EnvPath::try_from(Vec::<String>::new()), Err(ParseError::Unresolved));
+    }
+
+    #[test]
+    #[cfg(all(feature = "value", feature = "rand"))]
+    fn strict_try_from_resolves_once() {
+        use crate::random::{clear_seed_for_testing, get_random_value_seeded, seed_for_testing};
+
+        // The value that is checked is the value of the path, i.e. the first one drawn.
+        seed_for_testing(2029);
+        let path = EnvPath::try_from_slice(&["$val: rand-8", "app"]);
+        clear_seed_for_testing();
+
+        let expected = std::path::Path::new(&get_random_value_seeded(Some(8), 2029)).join("app");
+        assert_eq!(path.unwrap().path, Some(expected));
+    }
+
+    #[test]
+    #[cfg(all(feature = "consts", feature = "project"))]
+    fn try_from_path_str() {
+        use crate::ParseError;
+        use std::path::Path;
+
+        let raw = |s| {
+            EnvPath::try_from(s)
+                .map(|p| p.get_raw().iter().map(String::from).collect::<Vec<_>>())
+        };
+
+        assert_eq!(
+            raw("$const: empty/a//b/").unwrap(),
+            ["$const: empty", "a", "b"]
+        );
+        assert_eq!(
+            raw("/srv/$const: empty/x").unwrap(),
+            ["/", "srv", "$const: empty", "x"]
+        );
+        // `(` and `)` are not split.
+        assert_eq!(
+            raw("$proj(com/x/y): cfg/a").unwrap(),
+            ["$proj(com/x/y): cfg", "a"]
+        );
+        // The inline default is literal.
+        assert_eq!(
+            raw("$env: envpath_test_unset :- /tmp/fallback").unwrap(),
+            ["$env: envpath_test_unset :- /tmp/fallback"]
+        );
+
+        let p = EnvPath::try_from("/srv/$const: empty/x").unwrap();
+        assert_eq!(p.path.as_deref(), Some(Path::new("/srv/x")));
+
+        assert_eq!(
+            EnvPath::try_from("a/$env: envpath_test_unset/b"),
+            Err(ParseError::UnresolvedChunk("$env: envpath_test_unset".into()))
+        );
+
+        // On Unix, `\\` is a part of the file name.
+        #[cfg(unix)]
+        assert_eq!(raw("a\\b/c").unwrap(), ["a\\b", "c"]);
+        #[cfg(windows)]
+        assert_eq!(
+            raw(r"C:\Users\$const: empty/x").unwrap(),
+            [r"C:\", "Users", "$const: empty", "x"]
+        );
+    }
+
     #[test]
     fn from_iter_ref() {
         let arr = vec!["$dir: data", "test"];
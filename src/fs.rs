@@ -0,0 +1,511 @@
+use crate::{
+    parser::{parse_with, split_alternatives, ParseCtx},
+    EnvPath,
+};
+use std::{
+    fs, io, iter,
+    path::{Path, PathBuf},
+};
+
+impl EnvPath<'_> {
+    /// Walks up from the resolved path and returns the deepest ancestor that is an existing directory.
+    ///
+    /// If the resolved path itself is an existing directory, it is returned as is.
+    /// Returns `None` if the path has not been resolved, or if none of its ancestors exist.
+    ///
+    /// This is useful for "create the missing tail" workflows, where you need to know where to start creating.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["$dir: data", "envpath-doc", "a", "b"]);
+    /// let ancestor = path.deepest_existing_ancestor();
+    /// dbg!(&ancestor);
+    /// ```
+    pub fn deepest_existing_ancestor(&self) -> Option<PathBuf> {
+        self.path
+            .as_deref()?
+            .ancestors()
+            .find(|p| p.is_dir())
+            .map(Path::to_path_buf)
+    }
+
+    /// Returns every alternative of the `?`/`??` chain in the first chunk whose path exists, in order, rather than only the first one.
+    ///
+    /// e.g. For `["$env: xdg-config-home ? home", "app"]`, the candidates are `$env: xdg-config-home/app` and `$env: home/app`.
+    /// The remaining chunks are appended to each alternative. Duplicate paths are only listed once.
+    ///
+    /// It is useful for "show all places this config was found" diagnostics.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$dir: cfg ? data ? envpath-none", "."]);
+    /// let found = path.existing_candidates();
+    /// dbg!(&found);
+    /// ```
+    pub fn existing_candidates(&self) -> Vec<PathBuf> {
+        let mut chunks = self.raw.iter();
+        let Some(first) = chunks.next() else {
+            return Vec::new();
+        };
+        let rest = chunks.collect::<Vec<_>>();
+
+        let mut found = Vec::new();
+
+        for chunk in split_alternatives(first) {
+            let iter = iter::once(chunk.as_ref()).chain(rest.iter().copied());
+
+            match parse_with(iter, ParseCtx::default()) {
+                Some(p) if p.exists() && !found.contains(&p) => found.push(p),
+                _ => {}
+            }
+        }
+        found
+    }
+
+    /// Resolves each candidate (via `de()`) in order, and returns the first one whose path exists.
+    ///
+    /// Unlike `??` (e.g. `$dir: cfg ?? data`), which only chooses between the alternatives within one chunk, each candidate here is an independent (multi-chunk) template, e.g. `["$dir: cfg", "app", "config.toml"]` and `["$env: home", ".app.toml"]`.
+    /// The remaining candidates are not resolved once one is found.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[cfg(feature = "dirs")] {
+    /// use envpath::EnvPath;
+    ///
+    /// let found = EnvPath::first_existing([
+    ///     EnvPath::from(["$dir: cfg", "envpath-none", "config.toml"]),
+    ///     EnvPath::from(["$env: home", ".envpath-none.toml"]),
+    ///     EnvPath::from(["$dir: tmp"]),
+    /// ]);
+    /// assert_eq!(found.map(|x| x.path), Some(EnvPath::new(["$dir: tmp"]).path));
+    /// # }
+    /// ```
+    pub fn first_existing<'r, I>(candidates: I) -> Option<EnvPath<'r>>
+    where
+        I: IntoIterator<Item = EnvPath<'r>>,
+    {
+        candidates
+            .into_iter()
+            .map(EnvPath::de)
+            .find(|x| x.is_resolved() && x.exists())
+    }
+
+    /// Returns the resolved path, or an error of `NotFound` if it has not been resolved.
+    pub(crate) fn resolved_or_not_found(&self) -> io::Result<&Path> {
+        self.path.as_deref().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "The EnvPath has not been resolved")
+        })
+    }
+
+    /// Returns the immediate target of the symlink that the resolved path points to, via [fs::read_link()].
+    ///
+    /// Unlike canonicalization, only one level of the link is followed.
+    ///
+    /// An error is returned if the path has not been resolved (`NotFound`), or if it is not a symlink.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["$dir: data", "current"]);
+    /// let target = path.symlink_target();
+    /// dbg!(target);
+    /// ```
+    pub fn symlink_target(&self) -> io::Result<PathBuf> {
+        fs::read_link(self.resolved_or_not_found()?)
+    }
+
+    /// Returns the canonical, absolute form of the resolved path, with all intermediate components normalized and symlinks resolved, via [fs::canonicalize()].
+    ///
+    /// An error of `NotFound` is returned if the path has not been resolved. Note that `Path::canonicalize()` (via `Deref`) would canonicalize an empty path instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["$dir: tmp"]);
+    /// let canonical = path.canonicalize();
+    /// dbg!(&canonical);
+    ///
+    /// assert!(EnvPath::from(["$dir: tmp"]).canonicalize().is_err());
+    /// ```
+    pub fn canonicalize(&self) -> io::Result<PathBuf> {
+        fs::canonicalize(self.resolved_or_not_found()?)
+    }
+
+    /// Similar to `canonicalize()`, but the path does not need to fully exist.
+    ///
+    /// The longest existing leading portion of the resolved path is canonicalized (e.g. its symlinks are resolved), and the remaining (non-existing) components are appended verbatim.
+    /// It is useful for targeting a not-yet-created file in a real directory.
+    ///
+    /// Returns `None` if the path has not been resolved, or if none of its ancestors can be canonicalized. A relative path is relative to the current dir.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["$dir: tmp", "envpath-none", "a.toml"]);
+    /// let target = path.canonicalize_existing_prefix();
+    /// dbg!(&target);
+    /// ```
+    pub fn canonicalize_existing_prefix(&self) -> Option<PathBuf> {
+        let path = self.path.as_deref()?;
+
+        path.ancestors().find_map(|prefix| {
+            let canonical = if prefix.as_os_str().is_empty() {
+                fs::canonicalize(".")
+            } else {
+                fs::canonicalize(prefix)
+            };
+            let rest = path.strip_prefix(prefix).ok()?;
+            canonical.ok().map(|p| p.join(rest))
+        })
+    }
+
+    /// The consuming version of `canonicalize()`: the resolved path is replaced with its canonical form, while `raw` is kept intact.
+    ///
+    /// It only works after `de()`, and needs filesystem access. If the path does not exist (or has not been resolved), the original error is returned, rather than silently keeping the uncanonicalized path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["$dir: tmp", ".", "."]).canonicalized();
+    /// dbg!(&path);
+    /// ```
+    pub fn canonicalized(self) -> io::Result<Self> {
+        let path = self.canonicalize()?;
+
+        Ok(Self {
+            raw: self.raw,
+            path: Some(path),
+        })
+    }
+
+    /// Recursively creates the resolved directory and all of its missing parents, via [fs::create_dir_all()], and returns the path for chaining.
+    ///
+    /// It is a no-op if the directory already exists. An error of `NotFound` is returned if the path has not been resolved.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["$proj(com.x.y): data", "db"]);
+    /// let dir = path.create_dir_all()?;
+    /// dbg!(dir);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn create_dir_all(&self) -> io::Result<&Path> {
+        let path = self.resolved_or_not_found()?;
+        fs::create_dir_all(path)?;
+        Ok(path)
+    }
+
+    /// Similar to `create_dir_all()`, but only creates the parent of the resolved path, and returns the parent.
+    ///
+    /// It is for an `EnvPath` that points at a file (e.g. `config.ron`). An error of `NotFound` is returned if the path has not been resolved, and `InvalidInput` if it has no parent (e.g. `/`).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["$proj(com.x.y): cfg", "config.ron"]);
+    /// let dir = path.ensure_parent_dir()?;
+    /// dbg!(dir);
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn ensure_parent_dir(&self) -> io::Result<&Path> {
+        let parent = self
+            .resolved_or_not_found()?
+            .parent()
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "The path has no parent")
+            })?;
+        fs::create_dir_all(parent)?;
+        Ok(parent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::EnvPath;
+    use std::{
+        borrow::Cow,
+        env, fs,
+        ops::Deref,
+        path::{Path, PathBuf},
+        process,
+    };
+
+    /// A temp tree of the current process (i.e. `[dir]/envpath_[name]_[pid]`), which is removed on drop, even if the test fails.
+    struct TempTree {
+        root: PathBuf,
+    }
+
+    impl TempTree {
+        /// The tree is in `env::temp_dir()`. The root is not created yet.
+        fn new(name: &str) -> Self {
+            Self::new_in(env::temp_dir(), name)
+        }
+
+        fn new_in<P: AsRef<Path>>(dir: P, name: &str) -> Self {
+            Self {
+                root: dir
+                    .as_ref()
+                    .join(format!("envpath_{name}_{}", process::id())),
+            }
+        }
+
+        /// Creates `sub` (e.g. `a/b`) and its ancestors, and returns its path.
+        fn create(&self, sub: &str) -> PathBuf {
+            let dir = self.root.join(sub);
+            fs::create_dir_all(&dir).expect("Failed to create the temp tree");
+            dir
+        }
+
+        /// The root as a raw chunk of `EnvPath`.
+        fn raw(&self) -> Cow<'_, str> {
+            self.root.to_string_lossy()
+        }
+
+        /// The file name of the root, e.g. `envpath_create_1234`.
+        #[cfg(feature = "dirs")]
+        fn name(&self) -> Cow<'_, str> {
+            self.root
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        }
+    }
+
+    impl Deref for TempTree {
+        type Target = Path;
+
+        fn deref(&self) -> &Self::Target {
+            &self.root
+        }
+    }
+
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn deepest_existing_ancestor_of_partial_tree() {
+        let tree = TempTree::new("ancestor");
+        let existing = tree.create("a/b");
+
+        let root_str = tree.raw();
+        let path = EnvPath::new([root_str.as_ref(), "a", "b", "c", "d"]);
+
+        assert!(!path.exists());
+        assert_eq!(path.deepest_existing_ancestor(), Some(existing.clone()));
+
+        let full = EnvPath::new([root_str.as_ref(), "a", "b"]);
+        assert_eq!(full.deepest_existing_ancestor(), Some(existing));
+
+        assert_eq!(EnvPath::default().deepest_existing_ancestor(), None);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlink_target_of_temp_link() {
+        use std::{io::ErrorKind, os::unix::fs::symlink};
+
+        let tree = TempTree::new("symlink");
+        let target = tree.create("v1");
+        symlink(&target, tree.join("current")).expect("Failed to create the symlink");
+
+        let root_str = tree.raw();
+        let link = EnvPath::new([root_str.as_ref(), "current"]);
+        assert_eq!(link.symlink_target().ok(), Some(target));
+
+        // Not a symlink
+        let dir = EnvPath::new([root_str.as_ref(), "v1"]);
+        assert!(dir.symlink_target().is_err());
+
+        let raw = EnvPath::from([root_str.as_ref(), "current"]);
+        assert_eq!(
+            raw.symlink_target().map_err(|e| e.kind()),
+            Err(ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "dirs")]
+    fn canonicalize_tmp_subdir() {
+        use std::io::ErrorKind;
+
+        let tree = TempTree::new_in(EnvPath::new(["$dir: tmp"]), "canonicalize");
+        let sub = &*tree.name();
+        let path = EnvPath::new(["$dir: tmp", sub, "a", ".."]);
+        let dir = EnvPath::new(["$dir: tmp", sub]);
+
+        tree.create("a");
+
+        let canonical = path
+            .canonicalize()
+            .expect("Failed to canonicalize");
+        assert_eq!(Some(canonical.clone()), fs::canonicalize(&*dir).ok());
+        assert!(canonical.is_absolute());
+        assert!(canonical.ends_with(sub));
+
+        assert_eq!(
+            EnvPath::from(["$dir: tmp"])
+                .canonicalize()
+                .map_err(|e| e.kind()),
+            Err(ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "dirs")]
+    fn create_nested_dir_twice() {
+        use std::io::ErrorKind;
+
+        let tree = TempTree::new_in(EnvPath::new(["$dir: tmp"]), "create");
+        let sub = &*tree.name();
+        let path = EnvPath::new(["$dir: tmp", sub, "a", "b"]);
+
+        let created = path
+            .create_dir_all()
+            .expect("Failed to create the dir");
+        assert!(created.is_dir());
+
+        // It is idempotent.
+        assert_eq!(path.create_dir_all().ok(), path.path.as_deref());
+
+        assert_eq!(
+            EnvPath::from(["$dir: tmp", sub])
+                .create_dir_all()
+                .map_err(|e| e.kind()),
+            Err(ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn canonicalize_prefix_through_symlink() {
+        use std::os::unix::fs::symlink;
+
+        let tree = TempTree::new("canonical_prefix");
+        let real = tree.create("real");
+        symlink(&real, tree.join("link")).expect("Failed to create the symlink");
+
+        let root_str = tree.raw();
+        let path = EnvPath::new([root_str.as_ref(), "link", "new", "file.toml"]);
+        assert!(path.canonicalize().is_err());
+
+        let canonical_real = fs::canonicalize(&real).expect("Failed to canonicalize");
+        assert_eq!(
+            path.canonicalize_existing_prefix(),
+            Some(canonical_real.join("new").join("file.toml"))
+        );
+
+        // It is the same as `canonicalize()` if the path exists.
+        let link = EnvPath::new([root_str.as_ref(), "link"]);
+        assert_eq!(link.canonicalize_existing_prefix(), link.canonicalize().ok());
+
+        assert_eq!(EnvPath::from(["a"]).canonicalize_existing_prefix(), None);
+    }
+
+    #[test]
+    fn first_existing_candidate() {
+        let tree = TempTree::new("first_existing");
+        tree.create("b");
+        tree.create("c");
+
+        let root_str = tree.raw();
+        let candidate = |x: &str| EnvPath::from(&[root_str.as_ref(), x][..]);
+
+        let found = EnvPath::first_existing(["a", "b", "c"].map(candidate));
+        assert_eq!(found.and_then(|x| x.path), Some(tree.join("b")));
+
+        assert_eq!(EnvPath::first_existing(["a", "d"].map(candidate)), None);
+        assert_eq!(EnvPath::first_existing([EnvPath::default()]), None);
+    }
+
+    #[test]
+    fn canonicalized_keeps_raw() {
+        let tree = TempTree::new("canonicalized");
+        tree.create("a");
+
+        let root_str = tree.raw();
+        let raw = [root_str.as_ref(), "a", ".."];
+        let path = EnvPath::new(raw)
+            .canonicalized()
+            .expect("Failed to canonicalize");
+
+        assert_eq!(path.get_raw(), EnvPath::from(raw).get_raw());
+        assert_eq!(path.path, fs::canonicalize(&*tree).ok());
+
+        let missing = EnvPath::new([root_str.as_ref(), "missing"]).canonicalized();
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn ensure_parent_dir_of_file() {
+        use std::io::ErrorKind;
+
+        let tree = TempTree::new("parent");
+        let root_str = tree.raw();
+        let path = EnvPath::new([root_str.as_ref(), "a", "config.ron"]);
+
+        let parent = path
+            .ensure_parent_dir()
+            .expect("Failed to create the parent dir");
+        assert_eq!(parent, tree.join("a"));
+        assert!(parent.is_dir());
+        // Only the parent is created.
+        assert!(!path.exists());
+
+        assert_eq!(
+            EnvPath::from([root_str.as_ref(), "config.ron"])
+                .ensure_parent_dir()
+                .map_err(|e| e.kind()),
+            Err(ErrorKind::NotFound)
+        );
+    }
+
+    #[test]
+    fn existing_candidates_of_chain() {
+        let tree = TempTree::new("candidates");
+        let (a, b, c) = (tree.join("a"), tree.join("b"), tree.join("c"));
+        tree.create("a/app");
+        tree.create("c/app");
+
+        env::set_var("ENVPATH_TEST_CAND_A", &a);
+        env::set_var("ENVPATH_TEST_CAND_B", &b);
+        env::set_var("ENVPATH_TEST_CAND_C", &c);
+
+        let path = EnvPath::from([
+            "$env: envpath-test-cand-a ? envpath-test-cand-b ?? envpath-test-cand-c",
+            "app",
+        ]);
+        assert_eq!(path.existing_candidates(), [a.join("app"), c.join("app")]);
+
+        // Without a chain, it is the resolved path (if it exists).
+        let path = EnvPath::from(["$env: envpath-test-cand-b"]);
+        assert!(path.existing_candidates().is_empty());
+        assert!(EnvPath::default().existing_candidates().is_empty());
+
+        for var in ["ENVPATH_TEST_CAND_A", "ENVPATH_TEST_CAND_B", "ENVPATH_TEST_CAND_C"] {
+            env::remove_var(var)
+        }
+    }
+}
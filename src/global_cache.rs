@@ -0,0 +1,291 @@
+//! A process-level cache of resolutions, which is keyed by `(rule, env-snapshot-hash)`.
+//!
+//! Unlike [ResolverCache](crate::ResolverCache), it does not need to be passed around. The entries of an old environment are not used once the snapshot is [refreshed](refresh), since the hash of the snapshot changes.
+//!
+//! Hashing the snapshot reads and sorts every environment variable of the process, so it is only done on the first `de_cached()` and on [refresh()], rather than on every call. After changing the environment (e.g. with `env::set_var()`), call [refresh()]. The `:=` of [ParseOptions::allow_env_mutation()](crate::ParseOptions::allow_env_mutation) refreshes it automatically.
+//!
+//! It is bounded, and the least recently used entry is evicted when it is full.
+//!
+//! Note: `??` checks whether the path exists, which is not a part of the snapshot. If the filesystem changes, call [clear()].
+//!
+//! # Examples
+//!
+//! ```
+//! use envpath::{global_cache, EnvPath};
+//!
+//! let a = EnvPath::from(["$dir: cfg", "a.toml"]).de_cached();
+//! let b = EnvPath::from(["$dir: cfg", "a.toml"]).de_cached();
+//!
+//! assert_eq!(a.path, b.path);
+//! assert_eq!(global_cache::misses(), 1);
+//! ```
+use crate::{cache::ResolverCache, parser::is_special_chunk, EnvPath};
+use std::{
+    collections::hash_map::{DefaultHasher, HashMap},
+    env,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock, PoisonError,
+    },
+};
+
+/// The default maximum number of entries.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+type Key = (Vec<String>, u64);
+
+#[derive(Debug)]
+struct Lru {
+    map: HashMap<Key, (Option<PathBuf>, u64)>,
+    /// Incremented on every access, the entry with the smallest tick is the least recently used.
+    tick: u64,
+    capacity: usize,
+}
+
+impl Lru {
+    fn get(&mut self, key: &Key) -> Option<Option<PathBuf>> {
+        self.tick += 1;
+        let tick = self.tick;
+
+        self.map.get_mut(key).map(|(v, t)| {
+            *t = tick;
+            v.clone()
+        })
+    }
+
+    fn insert(&mut self, key: Key, value: Option<PathBuf>) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        self.evict_to(self.capacity - 1);
+        self.map.insert(key, (value, self.tick));
+    }
+
+    /// Evicts the least recently used entries until there are at most `n` entries.
+    fn evict_to(&mut self, n: usize) {
+        while self.map.len() > n {
+            // The capacity is small, so a linear scan is enough.
+            let Some(oldest) = self
+                .map
+                .iter()
+                .min_by_key(|(_, (_, t))| *t)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            self.map.remove(&oldest);
+        }
+    }
+}
+
+static CACHE: OnceLock<Mutex<Lru>> = OnceLock::new();
+static MISSES: AtomicUsize = AtomicUsize::new(0);
+/// The hash of the environment snapshot, which is taken on the first `de_cached()` and re-taken by [refresh()].
+static SNAPSHOT: Mutex<Option<u64>> = Mutex::new(None);
+
+fn with_cache<T>(f: impl FnOnce(&mut Lru) -> T) -> T {
+    let cache = CACHE.get_or_init(|| {
+        Mutex::new(Lru {
+            map: HashMap::new(),
+            tick: 0,
+            capacity: DEFAULT_CAPACITY,
+        })
+    });
+    f(&mut cache
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Sets the maximum number of entries (default: [DEFAULT_CAPACITY]). If it is 0, nothing is cached.
+pub fn set_capacity(capacity: usize) {
+    with_cache(|c| {
+        c.capacity = capacity;
+        c.evict_to(capacity);
+    })
+}
+
+/// Returns the maximum number of entries.
+pub fn capacity() -> usize {
+    with_cache(|c| c.capacity)
+}
+
+/// Drops all entries.
+pub fn clear() {
+    with_cache(|c| c.map.clear())
+}
+
+/// Returns the number of entries.
+pub fn len() -> usize {
+    with_cache(|c| c.map.len())
+}
+
+/// Returns `true` if there are no entries.
+pub fn is_empty() -> bool {
+    len() == 0
+}
+
+/// Returns how many times a rule had to be resolved (i.e. the number of misses).
+pub fn misses() -> usize {
+    MISSES.load(Ordering::Relaxed)
+}
+
+/// Re-hashes the snapshot of the environment variables, so that the entries of the old environment are no longer used. Call it after changing the environment.
+///
+/// The old entries are not dropped (call [clear()] for that), they are evicted once they become the least recently used.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::{global_cache, EnvPath};
+/// use std::env;
+///
+/// env::set_var("ENVPATH_DOC_REFRESH", "/srv/a");
+/// global_cache::refresh();
+/// let a = EnvPath::from(["$env: envpath-doc-refresh"]).de_cached();
+///
+/// env::set_var("ENVPATH_DOC_REFRESH", "/srv/b");
+/// global_cache::refresh();
+/// let b = EnvPath::from(["$env: envpath-doc-refresh"]).de_cached();
+///
+/// assert_eq!(a.to_str(), Some("/srv/a"));
+/// assert_eq!(b.to_str(), Some("/srv/b"));
+/// env::remove_var("ENVPATH_DOC_REFRESH");
+/// ```
+pub fn refresh() {
+    *SNAPSHOT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner) = Some(env_snapshot_hash())
+}
+
+/// Returns the hash of the snapshot, and takes it if there is none yet.
+pub(crate) fn snapshot() -> u64 {
+    *SNAPSHOT
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get_or_insert_with(env_snapshot_hash)
+}
+
+/// Hashes all the environment variables of the process. The order of them does not matter.
+///
+/// It is O(n log n) in the number of variables, see [snapshot()].
+pub(crate) fn env_snapshot_hash() -> u64 {
+    let mut vars = env::vars_os().collect::<Vec<_>>();
+    vars.sort_unstable();
+
+    let mut hasher = DefaultHasher::new();
+    vars.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Resolves the raw chunks of `path`, consulting the cache entry of `snapshot` first.
+pub(crate) fn resolve(path: &EnvPath, snapshot: u64) -> Option<PathBuf> {
+    let raw = path.get_raw();
+
+    // The values that may change at runtime (e.g. `$val`, `$dir: cwd` or `??`) must not be shared.
+    if raw
+        .iter()
+        .any(|x| is_special_chunk(x) && !ResolverCache::is_cacheable(x))
+    {
+        return raw.parse();
+    }
+
+    let key = (raw.iter().map(|x| x.trim().to_owned()).collect(), snapshot);
+
+    if let Some(v) = with_cache(|c| c.get(&key)) {
+        return v;
+    }
+
+    MISSES.fetch_add(1, Ordering::Relaxed);
+    let value = raw.parse();
+    with_cache(|c| c.insert(key, value.clone()));
+
+    value
+}
+
+impl EnvPath<'_> {
+    /// Similar to `de()`, but the result is memoized in a process-level cache (see [global_cache](crate::global_cache)).
+    ///
+    /// If the same rule has been resolved with the same snapshot of the environment variables, the memoized path is reused. The snapshot is hashed once, so call [global_cache::refresh()](crate::global_cache::refresh) after changing the environment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$dir: data", "app"]).de_cached();
+    /// assert_eq!(path.path, EnvPath::new(["$dir: data", "app"]).path);
+    /// ```
+    pub fn de_cached(self) -> Self {
+        if self.raw.is_empty() {
+            return EnvPath {
+                raw: self.raw,
+                path: None,
+            };
+        }
+
+        let path = resolve(&self, snapshot());
+
+        Self {
+            raw: self.raw,
+            path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cache is global, so all the checks are in one test.
+    #[test]
+    fn cache_hit_avoids_resolution() {
+        let path = EnvPath::from(["$dir: cfg", "app"]);
+        let base = misses();
+
+        let first = resolve(&path, 1);
+        assert_eq!(misses(), base + 1);
+
+        // hit
+        assert_eq!(resolve(&path, 1), first);
+        assert_eq!(misses(), base + 1);
+
+        // The environment has changed.
+        resolve(&path, 2);
+        assert_eq!(misses(), base + 2);
+
+        // Random chunks are not cached.
+        let len = len();
+        resolve(&EnvPath::from(["$dir: tmp-rand"]), 1);
+        assert_eq!(len, super::len());
+        assert_eq!(misses(), base + 2);
+
+        // The least recently used entry (snapshot 2) is evicted.
+        resolve(&path, 1);
+        set_capacity(1);
+        assert_eq!(super::len(), 1);
+        resolve(&path, 1);
+        assert_eq!(misses(), base + 2);
+
+        set_capacity(DEFAULT_CAPACITY);
+        clear();
+        assert!(is_empty());
+    }
+
+    #[test]
+    fn snapshot_hash_changes_with_env() {
+        let before = env_snapshot_hash();
+        let taken = snapshot();
+        env::set_var("ENVPATH_TEST_SNAPSHOT", "1");
+        assert_ne!(before, env_snapshot_hash());
+
+        // The snapshot is only re-hashed on request.
+        refresh();
+        assert_ne!(taken, snapshot());
+
+        env::remove_var("ENVPATH_TEST_SNAPSHOT");
+        refresh();
+    }
+}
@@ -0,0 +1,68 @@
+use crate::EnvPath;
+use std::{ffi::OsString, path::PathBuf};
+
+impl EnvPath<'_> {
+    /// Consumes the `EnvPath` and returns the resolved path, without cloning it.
+    ///
+    /// `None` means that the raw sequence was empty, or that it has not been resolved (e.g. `de()` has not been called).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["dev"]);
+    /// assert_eq!(path.into_path_buf(), Some("dev".into()));
+    ///
+    /// let raw = EnvPath::from(["$env: home"]);
+    /// assert_eq!(raw.into_path_buf(), None);
+    /// ```
+    pub fn into_path_buf(self) -> Option<PathBuf> {
+        self.path
+    }
+
+    /// Consumes the `EnvPath` and returns the resolved path as an `OsString`.
+    ///
+    /// `None` means that the raw sequence was empty, or that it has not been resolved.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["dev"]);
+    /// assert_eq!(path.into_os_string(), Some("dev".into()));
+    /// ```
+    pub fn into_os_string(self) -> Option<OsString> {
+        self.path
+            .map(PathBuf::into_os_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::EnvPath;
+
+    #[test]
+    fn into_owned_path() {
+        let path = EnvPath::new(["$env: home", "dev"]);
+        let expected = path.path.clone();
+
+        assert_eq!(path.clone().into_path_buf(), expected);
+        assert_eq!(
+            path.into_os_string(),
+            expected.map(|p| p.into_os_string())
+        );
+
+        // The returned buffer is the same as the display output.
+        let path = EnvPath::new(["$const: empty", "a", "b"]);
+        let display = path.display().to_string();
+        assert_eq!(
+            path.into_path_buf().map(|p| p.display().to_string()),
+            Some(display)
+        );
+
+        assert_eq!(EnvPath::default().into_path_buf(), None);
+        assert_eq!(EnvPath::new([]).into_os_string(), None);
+    }
+}
@@ -12,6 +12,7 @@ The library also supports optional features for getting common system paths:
 - `consts` - Gets the value of some specific constants built into crate.
 - `project` - For generating project directories (user-specific data dir)
 - `dirs` - Provides standard directories on different platforms.
+- `unicode-separators` (default) - Also recognizes the fullwidth colon (`：`) and question mark (`？`). They may be mixed with the ASCII ones in one special chunk (e.g. `$const: none ？ os ? arch`), while the fullwidth chars of a literal chunk are kept as is. Disabling it leaves only the ASCII `:` and `?`, which makes parsing a little smaller and faster.
 
 
 ## Serialization and deserialization
@@ -31,6 +32,7 @@ cargo add ron
 Now let's try serialization.
 
 ```rust
+# #[cfg(feature = "serde")] {
         use envpath::EnvPath;
         use serde::{Deserialize, Serialize};
 
@@ -49,6 +51,7 @@ Now let's try serialization.
 
         std::fs::write("test.ron", ron_str)
             .expect("Failed to write the ron cfg to test.ron");
+# }
 ```
 
 The output result is: `(dir: Some(["$env: user ?? userprofile ?? home"]))`
@@ -70,6 +73,7 @@ If you want to save performance overhead, you can change the value of `dir` to `
 Next, let's try deserialization!
 
 ```rust
+# #[cfg(feature = "serde")] {
         use envpath::EnvPath;
         use serde::{Deserialize, Serialize};
         use std::fs::File;
@@ -80,6 +84,7 @@ Next, let's try deserialization!
             dir: Option<EnvPath<'a>>,
         }
 
+#       std::fs::write("test.ron", r#"(dir: Some(["$env: user ?? userprofile ?? home"]))"#).unwrap();
         let cfg: Cfg = ron::de::from_reader(
             File::open("test.ron").expect("Failed to open the file: text.ron"),
         )
@@ -92,6 +97,7 @@ Next, let's try deserialization!
                 println!("{}", x.display())
             }
         }
+# }
 ```
 
 The output result of the above function is:
@@ -126,15 +132,17 @@ If we change it to `$env: user ?? userprofile ? home`, even though the value of
 
 Use `$const:name` (such as `$const:arch`) or `$const:alias` (e.g. `$const:architecture`) to obtain constant values. These values are obtained at compile time rather than runtime.
 
-| name          | alias        | From                    | example                 |
-| ------------- | ------------ | ----------------------- | ----------------------- |
-| arch          | architecture | `consts::ARCH`          | x86_64, aarch64         |
-| deb-arch      | deb_arch     | `get_deb_arch()`        | amd64, arm64            |
-| os            |              | `consts::OS`            | linux, windows, android |
-| family        |              | `consts::FAMILY`        | unix, windows           |
-| exe_suffix    |              | `consts::EXE_SUFFIX`    | `.exe`, `.nexe`         |
-| exe_extension |              | `consts::EXE_EXTENSION` | exe                     |
-| empty         |              |                         | ""                      |
+| name                   | alias                  | From                    | example                    |
+| ---------------------- | ---------------------- | ----------------------- | -------------------------- |
+| arch                   | architecture           | `consts::ARCH`          | x86_64, aarch64            |
+| deb-arch               | deb_arch               | `get_deb_arch()`        | amd64, arm64               |
+| arch-alias:[ecosystem] | arch_alias:[ecosystem] | `get_arch_alias()`      | amd64 (docker), x64 (node) |
+| ver-padded:[version]   | ver_padded:[version]   | `get_padded_version()`  | 01.02.03 (1.2.3)           |
+| os                     |                        | `consts::OS`            | linux, windows, android    |
+| family                 |                        | `consts::FAMILY`        | unix, windows              |
+| exe_suffix             |                        | `consts::EXE_SUFFIX`    | `.exe`, `.nexe`            |
+| exe_extension          |                        | `consts::EXE_EXTENSION` | exe                        |
+| empty                  |                        |                         | ""                         |
 
 ## val
 
@@ -142,10 +150,12 @@ Use `$const:name` (such as `$const:arch`) or `$const:alias` (e.g. `$const:archit
 
 Use `$val:name` (e.g. `$val: rand-16`) to obtain the values. Unlike `$const:`, most of the values here are obtained at runtime.
 
-| name           | expr            | example          |
-| -------------- | --------------- | ---------------- |
-| `rand-[usize]` | `$val: rand-16` | 90aU0QqYnx1gPEgN |
-| empty          | `$val: empty`   | ""               |
+| name                     | expr                     | example          |
+| ------------------------ | ------------------------ | ---------------- |
+| `rand-[usize]`           | `$val: rand-16`          | 90aU0QqYnx1gPEgN |
+| `rand-base32-[usize]`    | `$val: rand-base32-8`    | K3QZ7MXA         |
+| `rand-base64url-[usize]` | `$val: rand-base64url-8` | q-8Zr_0a         |
+| empty                    | `$val: empty`            | ""               |
 
 > `$val: rand-[usize]` syntax requires the `rand` feature to be enabled.
 
@@ -186,12 +196,9 @@ The following syntax is currently supported:
 
 - `$const: exe_suffix ?   env * HOME ?   env * XDG_DATA_HOME ?   env * EXE_SUFFIX`
 - `$env: home ? xdg-data-home ? exe_suffix ?    const * exe_suffix`
-
-Not supported:
-
 - `$const: exe_suffix ? $env: home ? xdg-data-home ? exe_suffix`
 
-If it is supported, the parsing may become complicated and there could be confusion between `$env: exe_suffix` and `$const: exe_suffix`.
+In the last one, each segment can carry its own `$scheme:` prefix. A segment without a prefix inherits the leading scheme of the chunk, so `xdg-data-home` and `exe_suffix` are resolved as `$const: xdg-data-home` and `$const: exe_suffix`, rather than `$env:`.
 
 ## dirs
 
@@ -205,6 +212,8 @@ Many of these contents are obtained from [dirs](https://docs.rs/dirs/latest/dirs
 | name                     | alias                    | Windows `$dir`                                                      |
 | ------------------------ | ------------------------ | ------------------------------------------------------------------- |
 | home                     |                          | `C:\Users\m`                                                        |
+| cwd                      | current, pwd             | `env::current_dir()`                                                |
+| root                     |                          | `$SystemDrive\`: (`C:\`)                                            |
 | cache                    |                          | `$localappdata`:(`$home\AppData\Local`)                             |
 | cfg                      | config                   | `$appdata`: (`$home\AppData\Roaming`)                               |
 | data                     |                          | `$home\AppData\Roaming`                                             |
@@ -221,12 +230,15 @@ Many of these contents are obtained from [dirs](https://docs.rs/dirs/latest/dirs
 | pref                     | preference               | `$home\AppData\Roaming`                                             |
 | pub                      | public                   | `$home\Public`                                                      |
 | runtime                  |                          | None                                                                |
+| socket                   | sock                     | None                                                                |
 | state                    |                          | None                                                                |
+| log                      | logs                     | `$home\AppData\Local\[pkg]\logs`                                    |
+| scratch                  |                          | `$tmpdir\[pkg]-[pid]`                                               |
 | video                    |                          | `$home\Videos`                                                      |
 | music                    | audio                    | `$home\Music`                                                       |
 | template                 |                          | `$ms_dir\Windows\Templates`                                         |
 | tmp                      |                          | `$tmpdir`                                                           |
-| tmp-rand                 | tmp_random               | `$tmpdir\[random]`                                        |
+| tmp-rand                 | tmp_random               | `$tmpdir\[random]`                                                  |
 | temp                     | temporary                | `env::temp_dir()`                                                   |
 | cli-data                 | cli_data                 | `$home\AppData\Local`                                               |
 | cli-cfg                  | cli_config               | `$home\AppData\Local`                                               |
@@ -251,13 +263,27 @@ For example, `$proj(com. x. y): data` will generate a `data` directory for this
 */
 use std::{self, path::PathBuf};
 
+mod builder;
+mod cache;
+mod cmp;
 mod deref;
+mod display;
+mod error;
 mod from;
+mod fs;
+mod into;
+mod normalize;
 mod os_cow;
+mod options;
 mod os_env;
 mod parser;
 mod raw;
+mod timeout;
 
+pub use builder::{Category, SpecialBuilder};
+pub use cache::ResolverCache;
+pub use error::ParseError;
+pub use options::{ParseOptions, Platform};
 pub use os_cow::OsCow;
 pub use raw::EnvPathRaw as Raw;
 
@@ -273,8 +299,22 @@ pub use directories::ProjectDirs;
 #[cfg(feature = "dirs")]
 pub mod dirs;
 
+#[cfg(all(windows, feature = "winfolder"))]
+mod winfolder;
+
+#[cfg(all(unix, feature = "dirs"))]
+mod sudo;
+
+#[cfg(feature = "cache")]
+pub mod global_cache;
+
 #[cfg(feature = "serde")]
 mod serialisation;
+#[cfg(feature = "serde")]
+pub use serialisation::{resolved, WithResolved};
+
+#[cfg(any(feature = "value", feature = "rand"))]
+mod encoding;
 
 #[cfg(feature = "value")]
 mod value;
@@ -287,3 +327,20 @@ pub struct EnvPath<'r> {
     pub(crate) raw: Raw<'r>,
     pub path: Option<PathBuf>,
 }
+
+/// Resolves one raw expression (e.g. `$dir: cfg`) into a path, without constructing an `EnvPath`.
+///
+/// The whole string is one raw chunk, so it is the same as [EnvPath::parse_str()] (and `EnvPath::new([raw]).path`).
+///
+/// # Examples
+///
+/// ```
+/// let cfg = envpath::resolve("$dir: cfg");
+/// dbg!(&cfg);
+///
+/// # #[cfg(feature = "dirs")]
+/// assert_eq!(cfg, envpath::EnvPath::new(["$dir: cfg"]).path);
+/// ```
+pub fn resolve(raw: &str) -> Option<PathBuf> {
+    EnvPath::parse_str(raw)
+}
@@ -0,0 +1,81 @@
+use crate::EnvPath;
+use std::path::{Component, Path, PathBuf};
+
+/// Lexically folds the `.` and `..` components of `path`, without touching the filesystem.
+///
+/// A leading `/` (or Windows prefix) is preserved, `..` at the root is dropped (`/..` => `/`), and a leading `..` of a relative path (including a drive-relative path, e.g. `C:..`) is kept (`../x` => `../x`).
+pub(crate) fn normalize_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::with_capacity(path.as_os_str().len());
+
+    for c in path.components() {
+        match c {
+            Component::CurDir => {}
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                }
+                Some(Component::RootDir) => {}
+                _ => out.push(c),
+            },
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+impl EnvPath<'_> {
+    /// Collapses the `.` and `..` components of the resolved path, without touching the filesystem.
+    ///
+    /// Unlike `canonicalize()`, the path does not need to exist, and symlinks are not resolved, so `a/link/..` is always `a`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    /// use std::path::Path;
+    ///
+    /// let path = EnvPath::new(["a", "b", "..", ".", "c"]).normalized();
+    /// assert_eq!(path.path.as_deref(), Some(Path::new("a").join("c").as_path()));
+    /// ```
+    pub fn normalized(self) -> Self {
+        Self {
+            path: self
+                .path
+                .as_deref()
+                .map(normalize_path),
+            raw: self.raw,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lexical_normalization() {
+        let n = |s: &str| normalize_path(Path::new(s));
+
+        assert_eq!(n("a/b/../c"), Path::new("a/c"));
+        assert_eq!(n("../x"), Path::new("../x"));
+        assert_eq!(n("a/../../x"), Path::new("../x"));
+        assert_eq!(n("./a/./b/"), Path::new("a/b"));
+        assert_eq!(n("a/.."), Path::new(""));
+
+        #[cfg(unix)]
+        {
+            assert_eq!(n("/a/../../b"), Path::new("/b"));
+            assert_eq!(n("/.."), Path::new("/"));
+        }
+
+        #[cfg(windows)]
+        {
+            assert_eq!(n(r"C:\a\..\..\b"), Path::new(r"C:\b"));
+            assert_eq!(n(r"\\server\share\..\x"), Path::new(r"\\server\share\x"));
+        }
+
+        let path = EnvPath::new(["a", "b", "..", "c"]).normalized();
+        assert_eq!(path.path, Some(Path::new("a").join("c")));
+        assert_eq!(EnvPath::from(["a", ".."]).normalized().path, None);
+    }
+}
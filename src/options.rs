@@ -0,0 +1,842 @@
+use crate::{
+    parser::{self, EnvMap, ParseCtx},
+    timeout, EnvPath, ParseError, ResolverCache,
+};
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fmt,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// The callback of [ParseOptions::resolver()].
+type EnvResolver = dyn Fn(&str) -> Option<OsString> + Send + Sync;
+/// The callback of [ParseOptions::overrides()].
+type ChunkResolver = dyn Fn(&str) -> Option<PathBuf> + Send + Sync;
+
+/// A shared value (e.g. a callback) of [ParseOptions], which is compared and hashed by a unique id, since a closure cannot be compared.
+///
+/// Unlike the address, the id is never reused by another value, so a [ResolverCache] keyed on the options never confuses two callbacks.
+pub(crate) struct Shared<T: ?Sized> {
+    id: u64,
+    pub(crate) value: Arc<T>,
+}
+
+impl<T: ?Sized> Shared<T> {
+    pub(crate) fn new(value: Arc<T>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self {
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            value,
+        }
+    }
+}
+
+impl<T: ?Sized> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            value: Arc::clone(&self.value),
+        }
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for Shared<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Shared({})", self.id)
+    }
+}
+
+impl<T: ?Sized> PartialEq for Shared<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T: ?Sized> Eq for Shared<T> {}
+
+impl<T: ?Sized> Hash for Shared<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state)
+    }
+}
+
+/// The platforms that can be simulated by [ParseOptions::simulate_platform()].
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Platform {
+    Android,
+    Linux,
+    MacOs,
+    Windows,
+}
+
+impl Platform {
+    /// The fake home directory that is used when [ParseOptions::fake_home()] is not set.
+    ///
+    /// | Platform | home                |
+    /// | -------- | ------------------- |
+    /// | Android  | `/data/data`        |
+    /// | Linux    | `/home/envpath`     |
+    /// | MacOs    | `/Users/envpath`    |
+    /// | Windows  | `C:\Users\envpath`  |
+    pub const fn default_home(&self) -> &'static str {
+        match self {
+            Self::Android => "/data/data",
+            Self::Linux => "/home/envpath",
+            Self::MacOs => "/Users/envpath",
+            Self::Windows => r#"C:\Users\envpath"#,
+        }
+    }
+
+    /// The path separator of the platform.
+    pub const fn separator(&self) -> char {
+        match self {
+            Self::Windows => '\\',
+            _ => '/',
+        }
+    }
+}
+
+/// Options for [EnvPath::de_with_options()].
+///
+/// All the variants of `de()` (e.g. an in-memory env map, a resolver, overrides, a cache and a timeout) can be combined here, rather than via the separate `de_with_*()` methods.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::{EnvPath, ParseOptions, Platform};
+///
+/// let opts = ParseOptions::new().simulate_platform(Platform::Android);
+///
+/// let path = EnvPath::from(["$proj(com.x.y): data"]).de_with_options(&opts);
+///
+/// # #[cfg(feature = "project")]
+/// assert_eq!(path.to_str(), Some("/data/data/com.x.y"));
+/// ```
+#[derive(Debug, Clone, Default, Hash, Eq, PartialEq)]
+pub struct ParseOptions {
+    pub(crate) platform: Option<Platform>,
+    pub(crate) home: Option<PathBuf>,
+    pub(crate) dedup_random: bool,
+    pub(crate) require_absolute: bool,
+    pub(crate) allow_env_mutation: bool,
+    pub(crate) project_root: Option<PathBuf>,
+    pub(crate) dry_run: bool,
+    pub(crate) env_case_sensitive: bool,
+    pub(crate) default_project: Option<String>,
+    pub(crate) respect_sudo_user: bool,
+    pub(crate) env_first: bool,
+    pub(crate) env_map: Option<BTreeMap<String, OsString>>,
+    pub(crate) resolver: Option<Shared<EnvResolver>>,
+    pub(crate) overrides: Option<Shared<ChunkResolver>>,
+    pub(crate) cache: Option<Shared<ResolverCache>>,
+    pub(crate) timeout: Option<Duration>,
+}
+
+impl ParseOptions {
+    /// Creates the default options, which are the same as `de()`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forces `$proj` to use the layout of `platform`, rather than the layout of the current platform.
+    ///
+    /// This allows testing the Android/macOS/Windows project directories on a Linux CI.
+    /// The simulated directories are rooted at [fake_home()](ParseOptions::fake_home), and are joined with the separator of `platform`.
+    pub fn simulate_platform(mut self, platform: Platform) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// Sets the fake home directory for [simulate_platform()](ParseOptions::simulate_platform).
+    ///
+    /// If it is not set, [Platform::default_home()] is used.
+    pub fn fake_home<P: Into<PathBuf>>(mut self, home: P) -> Self {
+        self.home = Some(home.into());
+        self
+    }
+
+    /// If it is true, identical random chunks within one path (e.g. two `$val: rand-8`) resolve to the same value.
+    ///
+    /// By default (false), each random chunk generates its own value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{EnvPath, ParseOptions};
+    ///
+    /// let opts = ParseOptions::new().dedup_random(true);
+    /// let path = EnvPath::from(["$val: rand-8", "$val: rand-8"]).de_with_options(&opts);
+    ///
+    /// # #[cfg(feature = "value")]
+    /// # {
+    /// let mut components = path.iter();
+    /// assert_eq!(components.next(), components.next());
+    /// # }
+    /// ```
+    pub fn dedup_random(mut self, dedup: bool) -> Self {
+        self.dedup_random = dedup;
+        self
+    }
+
+    /// If it is true, [EnvPath::try_de_with_options()] returns [ParseError::NotAbsolute] when the resolved path is relative (or unresolved).
+    ///
+    /// This catches configs that accidentally resolve to a relative literal, e.g. a misspelled `$env` name.
+    pub fn require_absolute(mut self, required: bool) -> Self {
+        self.require_absolute = required;
+        self
+    }
+
+    /// Roots all `$proj(...)` directories under `root`, rather than deriving them from `ProjectDirs`.
+    ///
+    /// e.g. `$proj(com.x.y): cache` => `[root]/com.x.y/cache`, `$proj(com.x.y): cfg` => `[root]/com.x.y/config`.
+    ///
+    /// This is useful for relocatable (portable) installs and test fixtures. It takes precedence over [simulate_platform()](ParseOptions::simulate_platform).
+    pub fn project_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.project_root = Some(root.into());
+        self
+    }
+
+    /// Sets the default project identifier (e.g. `com.x.y`), which is used by a bare `$proj` (i.e. without the parenthesized identifier).
+    ///
+    /// e.g. `$proj: data` is the same as `$proj(com.x.y): data`. This avoids repeating the identifier in every rule of a single-app binary.
+    pub fn default_project<S: Into<String>>(mut self, id: S) -> Self {
+        self.default_project = Some(id.into());
+        self
+    }
+
+    /// If it is true, and the environment variable `SUDO_USER` is set, `$dir: home` is the home of that user (via `getpwnam`), rather than the home of root. Like the other variables of `$dir`, `SUDO_USER` is read from the [env map](ParseOptions::env_map) if one is set.
+    ///
+    /// This is useful for tools (e.g. installers) that are invoked with `sudo`, where the user usually means their own home. It is Unix only, and off by default.
+    pub fn respect_sudo_user(mut self, respect: bool) -> Self {
+        self.respect_sudo_user = respect;
+        self
+    }
+
+    /// If it is true, the base directories of `$dir` (e.g. `cfg`, `data`, `cache`, `home`) consult the corresponding environment variables directly (e.g. `XDG_CONFIG_HOME`, `HOME`), before falling back to `dirs`.
+    ///
+    /// This gives a predictable behavior in containers and CI, where the variables are set precisely. On Windows, `APPDATA`, `LOCALAPPDATA` and `USERPROFILE` are also consulted. A variable that is not an absolute path is ignored.
+    ///
+    /// If [respect_sudo_user()](ParseOptions::respect_sudo_user) is also set, and `SUDO_USER` is found, `$dir: home` is the home of that user rather than `HOME`, since `HOME` is usually the home of root under sudo. The other variables (e.g. `XDG_CONFIG_HOME`) are still consulted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{EnvPath, ParseOptions};
+    ///
+    /// std::env::set_var("XDG_STATE_HOME", "/srv/state");
+    ///
+    /// let opts = ParseOptions::new().env_first(true);
+    /// let path = EnvPath::from(["$dir: state"]).de_with_options(&opts);
+    ///
+    /// # #[cfg(all(unix, feature = "dirs"))]
+    /// assert_eq!(path.to_str(), Some("/srv/state"));
+    /// ```
+    pub fn env_first(mut self, first: bool) -> Self {
+        self.env_first = first;
+        self
+    }
+
+    /// If it is true, the resolution never touches the filesystem: `??` no longer checks whether the path exists, so it behaves the same as `?` (i.e. the first alternative with a value wins).
+    ///
+    /// This makes the resolution deterministic, e.g. when computing a path to create. See also [EnvPath::de_dry()].
+    pub fn dry_run(mut self, dry: bool) -> Self {
+        self.dry_run = dry;
+        self
+    }
+
+    /// If it is true, the ident of `$env` is used exactly as written, e.g. `$env: MyVar` resolves `$MyVar`, rather than `$MYVAR`.
+    ///
+    /// By default (false), the ident is uppercased and `-` is replaced with `_` (e.g. `$env: xdg-data-home` => `$XDG_DATA_HOME`). This matters on case-sensitive platforms where mixed-case variables are used deliberately.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{EnvPath, ParseOptions};
+    ///
+    /// std::env::set_var("EnvPath_Doc_Var", "/srv");
+    ///
+    /// let opts = ParseOptions::new().env_case_sensitive(true);
+    /// let path = EnvPath::from(["$env: EnvPath_Doc_Var"]).de_with_options(&opts);
+    ///
+    /// # #[cfg(unix)]
+    /// assert_eq!(path.to_str(), Some("/srv"));
+    /// ```
+    pub fn env_case_sensitive(mut self, sensitive: bool) -> Self {
+        self.env_case_sensitive = sensitive;
+        self
+    }
+
+    /// If it is true, `$env: name := default` assigns `default` to the environment variable `name` when it is unset (similar to `${VAR:=default}` of the shell), so subsequent resolutions see it.
+    ///
+    /// By default (false), `:=` is the same as `:-`, i.e. the default value is used without modifying the environment.
+    ///
+    /// Note: The assignment calls [std::env::set_var()], which is not thread-safe on some platforms (e.g. Linux). Other threads that read or write the environment at the same time may observe a torn value or crash, so only enable it before spawning any threads.
+    pub fn allow_env_mutation(mut self, allowed: bool) -> Self {
+        self.allow_env_mutation = allowed;
+        self
+    }
+
+    /// `$env` (and `env * name`) reads the environment variables from `vars`, rather than the process environment (see [EnvPath::de_with_env()]).
+    ///
+    /// Note: The keys are the names after conversion. e.g. `$env: xdg-data-home` => `XDG_DATA_HOME`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{EnvPath, ParseOptions};
+    /// use std::path::Path;
+    ///
+    /// let opts = ParseOptions::new()
+    ///     .env_map([("HOME", "/home/m")])
+    ///     .dry_run(true);
+    ///
+    /// let path = EnvPath::from(["$env: home ?? user", "dev"]).de_with_options(&opts);
+    /// assert_eq!(path.path, Some(Path::new("/home/m").join("dev")));
+    /// ```
+    pub fn env_map<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<OsString>,
+    {
+        self.env_map = Some(
+            vars.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        );
+        self
+    }
+
+    /// `f` is consulted when an environment variable of `$env` does not exist (see [EnvPath::de_with_resolver()]).
+    pub fn resolver<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> Option<OsString> + Send + Sync + 'static,
+    {
+        self.resolver = Some(Shared::new(Arc::new(f)));
+        self
+    }
+
+    /// `f` is consulted for every raw chunk before the built-in resolution (see [EnvPath::de_with_overrides()]).
+    pub fn overrides<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> Option<PathBuf> + Send + Sync + 'static,
+    {
+        self.overrides = Some(Shared::new(Arc::new(f)));
+        self
+    }
+
+    /// The lookups of the special chunks are memoized in the shared `cache` (see [EnvPath::de_with_cache()]).
+    ///
+    /// The memoized values are keyed on the other options as well, so a cache can be shared by different options (e.g. two env maps) without mixing their values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{EnvPath, ParseOptions, ResolverCache};
+    /// use std::sync::Arc;
+    ///
+    /// let cache = Arc::new(ResolverCache::new());
+    /// let opts = ParseOptions::new().cache(cache.clone());
+    ///
+    /// EnvPath::from(["$dir: cfg", "a"]).de_with_options(&opts);
+    /// EnvPath::from(["$dir: cfg", "b"]).de_with_options(&opts);
+    /// assert_eq!(cache.lookups(), 1);
+    /// ```
+    pub fn cache(mut self, cache: Arc<ResolverCache>) -> Self {
+        self.cache = Some(Shared::new(cache));
+        self
+    }
+
+    /// The resolution runs on a worker thread, and gives up after `timeout` (see [EnvPath::de_with_timeout()]).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The context of a resolution with these options.
+    pub(crate) fn ctx(&self) -> ParseCtx<'_> {
+        ParseCtx {
+            env_fallback: self
+                .resolver
+                .as_ref()
+                .map(|f| &*f.value as parser::EnvFallback),
+            env_map: self
+                .env_map
+                .as_ref()
+                .map(|m| m as &dyn EnvMap),
+            options: Some(self),
+            overrides: self
+                .overrides
+                .as_ref()
+                .map(|f| &*f.value as parser::ChunkOverride),
+        }
+    }
+
+    /// Parses the raw chunks with these options, except for the timeout.
+    pub(crate) fn parse<S: AsRef<str>, I: IntoIterator<Item = S>>(
+        &self,
+        iter: I,
+    ) -> Option<PathBuf> {
+        match &self.cache {
+            Some(cache) => cache.value.parse(iter, self.ctx()),
+            None => parser::parse_with(iter, self.ctx()),
+        }
+    }
+
+    /// Returns the simulated platform and its home directory.
+    #[cfg(feature = "project")]
+    pub(crate) fn simulated(&self) -> Option<(Platform, String)> {
+        let platform = self.platform?;
+        let home = match &self.home {
+            Some(h) => h.to_string_lossy().into_owned(),
+            _ => platform.default_home().to_owned(),
+        };
+        Some((platform, home))
+    }
+}
+
+impl EnvPath<'_> {
+    /// Similar to `de()`, but takes the [ParseOptions] into account.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{EnvPath, ParseOptions, Platform};
+    ///
+    /// let opts = ParseOptions::new()
+    ///     .simulate_platform(Platform::MacOs)
+    ///     .fake_home("/Users/m");
+    ///
+    /// let path = EnvPath::from(["$proj(com.x.y): cache"]).de_with_options(&opts);
+    /// dbg!(path.display());
+    /// ```
+    pub fn de_with_options(self, options: &ParseOptions) -> Self {
+        if self.raw.is_empty() {
+            return Self {
+                raw: self.raw,
+                path: None,
+            };
+        }
+
+        let path = match options.timeout {
+            Some(t) => {
+                let chunks = self.raw.iter().collect::<Vec<_>>();
+                timeout::parse_with_timeout(&chunks, options, t)
+            }
+            None => options.parse(self.raw.iter()),
+        };
+
+        Self {
+            raw: self.raw,
+            path,
+        }
+    }
+
+    /// Similar to `de()`, but all `$proj(...)` directories are rooted under `root` (see [ParseOptions::project_root()]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$proj(com.x.y): cache", "a"]).with_project_root("/opt/myapp");
+    ///
+    /// # #[cfg(all(unix, feature = "project"))]
+    /// assert_eq!(path.to_str(), Some("/opt/myapp/com.x.y/cache/a"));
+    /// ```
+    pub fn with_project_root<P: Into<PathBuf>>(self, root: P) -> Self {
+        self.de_with_options(&ParseOptions::new().project_root(root))
+    }
+
+    /// Similar to `de()`, but a bare `$proj` (e.g. `$proj: data`) uses `id` as the project identifier (see [ParseOptions::default_project()]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$proj: data", "db"]).with_default_project("com.x.y");
+    /// let same = EnvPath::new(["$proj(com.x.y): data", "db"]);
+    ///
+    /// # #[cfg(feature = "project")]
+    /// assert_eq!(path.path, same.path);
+    /// ```
+    pub fn with_default_project<S: Into<String>>(self, id: S) -> Self {
+        self.de_with_options(&ParseOptions::new().default_project(id))
+    }
+
+    /// Similar to `de()`, but the resolution never touches the filesystem (see [ParseOptions::dry_run()]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// // `de()` would skip `$env: home`, as the path does not exist.
+    /// let path = EnvPath::from(["$env: home ?? user", "envpath-missing"]).de_dry();
+    /// dbg!(path.display());
+    /// ```
+    pub fn de_dry(self) -> Self {
+        self.de_with_options(&ParseOptions::new().dry_run(true))
+    }
+
+    /// Similar to `de_with_options()`, but returns an error if the path cannot be resolved, or if it does not meet the [ParseOptions] (e.g. [require_absolute()](ParseOptions::require_absolute)).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{EnvPath, ParseError, ParseOptions};
+    ///
+    /// let opts = ParseOptions::new().require_absolute(true);
+    ///
+    /// let path = EnvPath::from(["$env: envpath-unknown-var", "cfg"]).try_de_with_options(&opts);
+    /// assert!(matches!(path, Err(ParseError::NotAbsolute(_))));
+    /// ```
+    pub fn try_de_with_options(
+        self,
+        options: &ParseOptions,
+    ) -> Result<Self, ParseError> {
+        let path = self.de_with_options(options);
+
+        match &path.path {
+            Some(p) if options.require_absolute && !p.is_absolute() => {
+                Err(ParseError::NotAbsolute(p.clone()))
+            }
+            None if options.require_absolute => {
+                Err(ParseError::NotAbsolute(PathBuf::new()))
+            }
+            None => Err(ParseError::Unresolved),
+            Some(_) => Ok(path),
+        }
+    }
+
+    /// Resolves a single raw chunk (e.g. `$dir: cfg`), and reports the scheme/ident-level errors.
+    ///
+    /// - `Ok(Some(value))`: The chunk is resolved.
+    /// - `Ok(None)`: The chunk is legitimately unresolved, so the caller should fall back, i.e. a literal chunk (e.g. `app`), an unset `$env` variable, a known ident that is unavailable on this machine (e.g. `$dir: cfg` if `dirs` cannot find it), or a `??` whose paths do not exist.
+    /// - `Err(UnknownScheme)`: The scheme (e.g. `$foo`) is unknown, or its feature is disabled.
+    /// - `Err(UnknownIdent)`: The ident cannot be resolved by its scheme (e.g. `$dir: foo`).
+    /// - `Err(ProjectDirUnavailable)`: `ProjectDirs` exists, but it does not provide the directory (e.g. `$proj(com.x.y): runtime` without `XDG_RUNTIME_DIR`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::{EnvPath, ParseError, ParseOptions};
+    ///
+    /// let opts = ParseOptions::new();
+    ///
+    /// # #[cfg(feature = "consts")]
+    /// assert!(matches!(EnvPath::try_resolve_one("$const: os", &opts), Ok(Some(_))));
+    /// assert_eq!(EnvPath::try_resolve_one("app", &opts), Ok(None));
+    /// assert!(matches!(
+    ///     EnvPath::try_resolve_one("$foo: bar", &opts),
+    ///     Err(ParseError::UnknownScheme(_))
+    /// ));
+    /// ```
+    pub fn try_resolve_one(
+        raw: &str,
+        opts: &ParseOptions,
+    ) -> Result<Option<OsString>, ParseError> {
+        if !parser::is_special_chunk(raw) {
+            return Ok(None);
+        }
+
+        let ctx = opts.ctx();
+        if let Some(x) = parser::resolve_chunk(raw, &mut String::new(), ctx) {
+            return Ok(Some(x.into_owned()));
+        }
+
+        // Every alternative (e.g. `a` and `b` of `$dir: a ?? b`) has failed, so find out whether it is unknown, or just unavailable.
+        for chunk in parser::split_alternatives(raw) {
+            let chunk = chunk.trim();
+            let (scheme, ident) = match parser::get_chunks(chunk)[..] {
+                [scheme, ident] => (scheme, ident),
+                _ => continue,
+            };
+
+            if !parser::is_known_scheme(scheme) {
+                return Err(ParseError::UnknownScheme(scheme.to_owned()));
+            }
+            // It is resolved, but the existence check of `??` has failed.
+            if parser::resolve_chunk(chunk, &mut String::new(), ctx).is_some() {
+                continue;
+            }
+            #[cfg(feature = "project")]
+            if scheme.starts_with("$proj") && Self::is_proj_dir_unavailable(scheme, ident, ctx) {
+                return Err(ParseError::ProjectDirUnavailable {
+                    ident: ident.to_owned(),
+                });
+            }
+            if !parser::any_ident(chunk, is_unavailable_ident) {
+                return Err(ParseError::UnknownIdent(ident.to_owned()));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Similar to `try_de()`, but every special chunk must be resolved, so the raw str is never used as the default value.
+    ///
+    /// The first chunk that fails is reported (see [try_resolve_one()](EnvPath::try_resolve_one)), and an unset `$env` variable is [ParseError::UnresolvedChunk].
+    pub(crate) fn try_de_strict(self) -> Result<Self, ParseError> {
+        let opts = ParseOptions::default();
+        let mut casing = String::new();
+        let mut path = PathBuf::with_capacity(16);
+
+        // Every chunk is resolved once, and its value is joined as `parser::join_chunk()` does.
+        for chunk in self.raw.iter() {
+            path = match Self::try_resolve_one(chunk, &opts)? {
+                Some(x) => path.join(parser::collapse_separators(&x)),
+                None if parser::is_special_chunk(chunk) => {
+                    return Err(ParseError::UnresolvedChunk(chunk.to_owned()))
+                }
+                None => parser::join_chunk(&path, chunk, &mut casing, opts.ctx())
+                    .ok_or(ParseError::Unresolved)?,
+            };
+        }
+
+        match self.raw.is_empty() {
+            true => Err(ParseError::Unresolved),
+            _ => Ok(Self {
+                raw: self.raw,
+                path: Some(path),
+            }),
+        }
+    }
+}
+
+/// Whether the ident of `scheme` is known, but its value may be unavailable on this machine (e.g. an unset `$env` variable, or `$dir: cfg` if `dirs` cannot find it).
+fn is_unavailable_ident(scheme: &str, ident: &str) -> bool {
+    match scheme {
+        "$env" => true,
+        #[cfg(feature = "dirs")]
+        "$dir" => EnvPath::is_known_dir(ident),
+        #[cfg(feature = "value")]
+        "$val" => EnvPath::is_known_value(ident),
+        _ => {
+            let _ = ident;
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_options_are_the_same_as_de() {
+        let raw = ["$env: home", "$const: os", "test"];
+        let path = EnvPath::from(raw).de_with_options(&ParseOptions::new());
+        assert_eq!(path, EnvPath::new(raw));
+    }
+
+    #[test]
+    #[cfg(feature = "value")]
+    fn dedup_random_chunks() {
+        let raw = ["$val: rand-8", "x", " $val: rand-8 ", "$val: rand-9"];
+
+        let path = EnvPath::from(raw).de_with_options(&ParseOptions::new().dedup_random(true));
+        let c = path.iter().collect::<Vec<_>>();
+        assert_eq!(c.len(), 4);
+        assert_eq!(c[0], c[2]);
+        assert_ne!(c[0], c[3]);
+
+        let path = EnvPath::from(raw).de_with_options(&ParseOptions::new());
+        let c = path.iter().collect::<Vec<_>>();
+        assert_ne!(c[0], c[2]);
+
+        // The memo also applies to a cached resolution.
+        let opts = ParseOptions::new()
+            .dedup_random(true)
+            .cache(Arc::new(ResolverCache::new()));
+        let path = EnvPath::from(["$val: rand-8", "$val: rand-8"]).de_with_options(&opts);
+        let c = path.iter().collect::<Vec<_>>();
+        assert_eq!(c.len(), 2);
+        assert_eq!(c[0], c[1]);
+    }
+
+    #[test]
+    fn require_absolute_path() {
+        let opts = ParseOptions::new().require_absolute(true);
+
+        let root = if cfg!(windows) { r"C:\srv" } else { "/srv" };
+        let path = EnvPath::from([root, "app"]).try_de_with_options(&opts);
+        assert_eq!(
+            path.ok().and_then(|p| p.path),
+            Some(PathBuf::from(root).join("app"))
+        );
+
+        let path = EnvPath::from(["srv", "app"]).try_de_with_options(&opts);
+        assert_eq!(
+            path,
+            Err(ParseError::NotAbsolute(PathBuf::from("srv").join("app")))
+        );
+
+        let path = EnvPath::default().try_de_with_options(&opts);
+        assert_eq!(path, Err(ParseError::NotAbsolute(PathBuf::new())));
+
+        // Without `require_absolute`, a relative path is fine.
+        let path = EnvPath::from(["srv"]).try_de_with_options(&ParseOptions::new());
+        assert!(path.is_ok());
+        assert_eq!(EnvPath::default().try_de(), Err(ParseError::Unresolved));
+    }
+
+    #[test]
+    #[cfg(feature = "project")]
+    fn rooted_project_dirs() {
+        let root = PathBuf::from("/opt/myapp");
+        let dir = root.join("com.x.y");
+
+        let de = |raw: &str| EnvPath::from([raw]).with_project_root(&root).path;
+
+        assert_eq!(de("$proj(com.x.y): path"), Some(dir.clone()));
+        assert_eq!(de("$proj(com.x.y): cache"), Some(dir.join("cache")));
+        assert_eq!(de("$proj(com.x.y): cfg"), Some(dir.join("config")));
+        assert_eq!(de("$proj(com.x.y): local-data"), Some(dir.join("data")));
+        assert_eq!(de("$proj(com.x.y): state"), Some(dir.join("state")));
+        assert_eq!(
+            de("$proj(com.x.y): none ? (com.a.b): data"),
+            Some(root.join("com.a.b").join("data"))
+        );
+
+        // It takes precedence over `simulate_platform()`.
+        let opts = ParseOptions::new()
+            .simulate_platform(Platform::Windows)
+            .project_root(&root);
+        let path = EnvPath::from(["$proj(com.x.y): data"]).de_with_options(&opts);
+        assert_eq!(path.path, Some(dir.join("data")));
+    }
+
+    #[test]
+    fn dry_run_never_checks_existence() {
+        use std::{cell::Cell, ffi::OsString};
+
+        let probes = Cell::new(0);
+        let resolver = |name: &str| {
+            probes.set(probes.get() + 1);
+            Some(OsString::from(format!("/envpath-missing/{name}")))
+        };
+        let raw = EnvPath::from(["$env: envpath-test-dry-a ?? envpath-test-dry-b"]);
+        let parse = |opts: &ParseOptions| {
+            probes.set(0);
+            raw.get_raw().parse_with(ParseCtx {
+                env_fallback: Some(&resolver),
+                options: Some(opts),
+                ..Default::default()
+            })
+        };
+
+        // `??` rejects the missing path of `a`, so `b` is probed as well.
+        let path = parse(&ParseOptions::new());
+        assert_eq!(path, Some(PathBuf::from("/envpath-missing/ENVPATH_TEST_DRY_B")));
+        assert_eq!(probes.get(), 2);
+
+        // In a dry run, the first alternative with a value wins.
+        let path = parse(&ParseOptions::new().dry_run(true));
+        assert_eq!(path, Some(PathBuf::from("/envpath-missing/ENVPATH_TEST_DRY_A")));
+        assert_eq!(probes.get(), 1);
+
+        let dry = EnvPath::from(["$env: envpath-test-dry-unset ?? home"]).de_dry();
+        assert_eq!(dry.path, EnvPath::new(["$env: home"]).path);
+    }
+
+    #[test]
+    fn env_case_sensitive_ident() {
+        let env = [("EnvPath_Test_Mixed", "/mixed"), ("ENVPATH_TEST_MIXED", "/upper")];
+
+        let raw = ["$env: EnvPath_Test_Mixed"];
+        let path = EnvPath::from(raw).de_with_options(&ParseOptions::new().env_map(env));
+        assert_eq!(path.to_str(), Some("/upper"));
+
+        let opts = ParseOptions::new()
+            .env_map(env)
+            .env_case_sensitive(true);
+        let path = EnvPath::from(raw).de_with_options(&opts);
+        assert_eq!(path.to_str(), Some("/mixed"));
+
+        // `-` is not replaced either.
+        let path = EnvPath::from(["$env: envpath-test-mixed"]).de_with_options(&opts);
+        assert_eq!(path.to_str(), Some("$env: envpath-test-mixed"));
+    }
+
+    #[test]
+    fn combined_settings() {
+        use std::path::Path;
+
+        let cache = Arc::new(ResolverCache::new());
+        let opts = ParseOptions::new()
+            .env_map([("ENVPATH_TEST_MAP_ROOT", "/srv/map")])
+            .resolver(|name| {
+                (name == "ENVPATH_TEST_RESOLVED").then(|| OsString::from("/srv/resolved"))
+            })
+            .overrides(|chunk| (chunk == "@root").then(|| PathBuf::from("/override")))
+            .dry_run(true)
+            .cache(cache.clone())
+            .timeout(Duration::from_secs(10));
+        let de = |raw: &[&str]| EnvPath::from(raw).de_with_options(&opts).path;
+
+        assert_eq!(
+            de(&["$env: envpath-test-map-root ?? home", "a"]),
+            Some(Path::new("/srv/map").join("a"))
+        );
+        // `PATH` is not in the map, and `??` does not touch the filesystem in a dry run.
+        assert_eq!(
+            de(&["$env: path ?? envpath-test-resolved", "b"]),
+            Some(Path::new("/srv/resolved").join("b"))
+        );
+        assert_eq!(de(&["@root", "c"]), Some(Path::new("/override").join("c")));
+
+        #[cfg(feature = "consts")]
+        {
+            de(&["$const: os"]);
+            de(&["$const: os", "d"]);
+            assert_eq!(cache.lookups(), 1);
+        }
+
+        // The same options (i.e. the same callbacks) are equal.
+        assert_eq!(opts.clone(), opts);
+        assert_ne!(opts.clone().resolver(|_| None), opts);
+    }
+
+    #[test]
+    fn try_resolve_one_chunk() {
+        let opts = ParseOptions::new();
+        let resolve = |raw| EnvPath::try_resolve_one(raw, &opts);
+
+        #[cfg(feature = "consts")]
+        assert_eq!(resolve("$const: os"), Ok(Some(std::env::consts::OS.into())));
+        #[cfg(feature = "dirs")]
+        assert_eq!(
+            resolve("$dir: none_such"),
+            Err(ParseError::UnknownIdent("none_such".into()))
+        );
+
+        assert_eq!(resolve("app"), Ok(None));
+        assert_eq!(resolve("$env: envpath_test_unset_one"), Ok(None));
+        assert_eq!(resolve("$foo: bar"), Err(ParseError::UnknownScheme("$foo".into())));
+        assert_eq!(
+            resolve("$env: envpath_test_unset_one ? $foo: bar"),
+            Err(ParseError::UnknownScheme("$foo".into()))
+        );
+
+        // Known, but unavailable on this machine.
+        #[cfg(feature = "value")]
+        assert_eq!(resolve("$val: base64url(ENVPATH_TEST_UNSET_ONE)"), Ok(None));
+        #[cfg(feature = "consts")]
+        assert_eq!(
+            resolve("$const: none_such ? env * ENVPATH_TEST_UNSET_ONE"),
+            Err(ParseError::UnknownIdent("none_such".into()))
+        );
+        // `epoch` is resolved, but it is not an existing path.
+        #[cfg(feature = "value")]
+        assert_eq!(resolve("$val: epoch ?? env * ENVPATH_TEST_UNSET_ONE"), Ok(None));
+    }
+}
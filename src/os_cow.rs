@@ -16,7 +16,12 @@ pub type OsCow<'a> = Option<Cow<'a, OsStr>>;
 /// # Examples
 ///
 ///```no_run
-/// use envpath::os_cow;
+/// # // `os_cow` is private to the crate, so the doctest uses a copy of it.
+/// # mod os_cow {
+/// #     pub fn from_str(s: &str) -> envpath::OsCow<'_> {
+/// #         Some(std::borrow::Cow::from(std::ffi::OsStr::new(s)))
+/// #     }
+/// # }
 /// use std::{borrow::Cow, ffi::OsStr};
 ///
 /// let str = "/usr/bin";
@@ -24,7 +29,7 @@ pub type OsCow<'a> = Option<Cow<'a, OsStr>>;
 ///
 /// assert_eq!(os_cow, Some(Cow::from(OsStr::new(str))));
 ///```
-pub(crate) fn from_str(s: &str) -> OsCow {
+pub(crate) fn from_str(s: &str) -> OsCow<'_> {
     Some(Cow::from(OsStr::new(s)))
 }
 
@@ -33,12 +38,18 @@ pub(crate) fn from_str(s: &str) -> OsCow {
 /// # Examples
 ///
 ///```no_run
+/// # // `os_cow` is private to the crate, so the doctest uses a copy of it.
+/// # mod os_cow {
+/// #     pub fn into_os_cow<'a, I: Into<std::ffi::OsString>>(s: I) -> envpath::OsCow<'a> {
+/// #         Some(std::borrow::Cow::from(s.into()))
+/// #     }
+/// # }
 ///  use std::{
 ///      borrow::Cow,
 ///      path::{Path, PathBuf},
 ///  };
 ///  let path = Path::new("/usr/bin");
-///  let os_cow = envpath::os_cow::into_os_cow(path);
+///  let os_cow = os_cow::into_os_cow(path);
 ///  let path_cow = Cow::from(path.as_os_str());
 ///  assert_eq!(os_cow, Some(path_cow));
 ///  let pathbuf = PathBuf::from("/usr/bin");
@@ -1,13 +1,17 @@
-use crate::{parser, EnvPath, OsCow};
-use std::{borrow::Cow, env::var_os, ops::ControlFlow, path::Path};
+use crate::{parser::ParseCtx, EnvPath, OsCow};
+use std::{
+    borrow::Cow, collections::HashMap, env::var_os, ffi::OsString, ops::ControlFlow,
+    path::{Path, PathBuf},
+};
 
 /// fullwidth question mark
+#[cfg(feature = "unicode-separators")]
 pub const FWQM: char = '\u{FF1F}';
 /// halfwidth question mark
 pub const HWQM: char = '\u{3F}';
 
 impl EnvPath<'_> {
-    pub(crate) const START_ARR: [&str; 5] = ["env", "dir", "const", "proj", "val"];
+    pub(crate) const START_ARR: [&'static str; 5] = ["env", "dir", "const", "proj", "val"];
     /// It's a function for parsing rules(e.g. `$env: user ? userprofile ?? home`).
     /// The `s` parameter in this function refers to all strings in the closed interval from **user** to **home**. Does not contain the `$env:`.
     ///
@@ -23,11 +27,13 @@ impl EnvPath<'_> {
     /// If acc is Some, and x is not empty, exit the iterator. (If the value of the previous environment variable exists, we do not check the value of x this time, but return the value of the previous one)
     ///
     /// If acc is Some and x is empty, then determine if the file exists. If it does, we exit the iterator. If not, then acc is None.
-    pub(crate) fn parse_dir_rules<F>(
-        s: &str,
+    /// In a dry run (see `ParseOptions::dry_run()`), the file is not checked, so we always exit the iterator.
+    pub(crate) fn parse_dir_rules<'a, F>(
+        s: &'a str,
         f: F,
         separator: char, // Use a single char instead of pattern([char, char])
-    ) -> ControlFlow<OsCow, OsCow>
+        ctx: ParseCtx,
+    ) -> ControlFlow<OsCow<'a>, OsCow<'a>>
     where
         F: Fn(&str) -> OsCow,
     {
@@ -40,15 +46,27 @@ impl EnvPath<'_> {
                 (None, false) => Continue(f(x)),
                 (p, false) => Break(p),
                 (Some(p), true) => match Path::new(&p) {
-                    x if x.exists() => Break(Some(p)),
+                    x if ctx.is_dry_run() || x.exists() => Break(Some(p)),
                     _ => Continue(None),
                 },
             })
     }
 
+    /// The question mark is detected here for the same reason as the colon.
+    ///
+    /// If the `unicode-separators` feature is disabled, only the halfwidth question mark is detected.
+    #[cfg(not(feature = "unicode-separators"))]
+    pub(crate) fn get_question_mark_separator(s: &str) -> char {
+        match s.find(HWQM) {
+            Some(_) => HWQM,
+            _ => ' ',
+        }
+    }
+
     /// The question mark is detected here for the same reason as the colon.
     ///
     /// If someone forgets to switch the Chinese input method to English, it is easy to type '?' as '？'.
+    #[cfg(feature = "unicode-separators")]
     pub(crate) fn get_question_mark_separator(s: &str) -> char {
         let fq = FWQM;
         let hq = HWQM;
@@ -64,7 +82,11 @@ impl EnvPath<'_> {
     /// This function is used to handle ident starting with `env *` or `env*`, and then resolve the environment variable to the right of `*`
     ///
     /// Assuming that the ident is `env * home`, it does not automatically convert `home` to `HOME`, but gets `$home` directly.
-    pub(crate) fn handle_remix<'a>(s: &'a str, start: &str) -> OsCow<'a> {
+    pub(crate) fn handle_remix<'a>(
+        s: &'a str,
+        start: &str,
+        ctx: ParseCtx,
+    ) -> OsCow<'a> {
         match s
             .trim_start_matches(start)
             .trim()
@@ -72,23 +94,18 @@ impl EnvPath<'_> {
             x if x.starts_with('*') => {
                 let trimed = x.trim_start_matches('*').trim();
                 match start {
-                    "env" => Self::into_os_env(trimed),
+                    "env" => Self::lookup_env(trimed, ctx),
                     #[cfg(feature = "dirs")]
-                    "dir" => Self::match_base_dirs(trimed),
+                    "dir" => Self::match_base_dirs_ctx(trimed, ctx),
                     #[cfg(feature = "project")]
-                    "proj" => match parser::get_chunks(trimed) {
+                    "proj" => match crate::parser::get_chunks(trimed) {
                         c if matches!(c.len(), 0 | 1) => None,
-                        c => match Self::set_proj_name_opt_tuple(c[0]) {
-                            Some((name, proj)) => {
-                                Self::match_proj_dirs(c[1], &name, proj.as_ref())
-                            }
-                            _ => None,
-                        },
+                        c => Self::handle_project_dirs(c[0], c[1], ctx),
                     },
                     #[cfg(feature = "consts")]
-                    "const" => Self::match_consts(trimed),
+                    "const" => Self::match_consts(trimed, ctx),
                     #[cfg(feature = "value")]
-                    "val" => Self::match_values(trimed),
+                    "val" => Self::match_values(trimed, ctx),
                     _ => None,
                 }
             }
@@ -107,41 +124,182 @@ impl EnvPath<'_> {
         }
     }
 
-    pub(crate) fn parse_remix_expr(x: &str) -> OsCow {
+    /// Resolves a remix expression (e.g. `env * home` or `dir * cfg`), taking the options of `ctx` into account.
+    pub(crate) fn parse_remix_expr<'a>(x: &'a str, ctx: ParseCtx) -> OsCow<'a> {
         Self::START_ARR
             .iter()
             // .inspect(|x| println!("in: {x}"))
             .filter(|&start| x.starts_with(start))
             // .inspect(|x| println!("out: {x}"))
-            .find_map(|start| Self::handle_remix(x, start))
+            .find_map(|start| Self::handle_remix(x, start, ctx))
     }
 
-    pub(crate) fn into_os_env(x: &str) -> OsCow {
+    pub(crate) fn into_os_env(x: &str) -> OsCow<'_> {
         var_os(x).map(Cow::from)
     }
 
-    fn match_os_env(ident: &str) -> OsCow {
+    /// Gets the value of the environment variable.
+    ///
+    /// If `ctx` carries an in-memory map, the map is used instead of the process environment.
+    /// If neither has the variable, the user-supplied callback is consulted.
+    pub(crate) fn lookup_env<'a>(x: &'a str, ctx: ParseCtx) -> OsCow<'a> {
+        match ctx.env_map {
+            Some(map) => map
+                .get_var(x)
+                .and_then(crate::os_cow::into_os_cow),
+            _ => Self::into_os_env(x),
+        }
+        .or_else(|| Self::env_fallback(x, ctx))
+    }
+
+    fn match_os_env<'a>(ident: &'a str, ctx: ParseCtx) -> OsCow<'a> {
         match ident {
             x if Self::starts_with_remix_expr(x) => {
                 // dbg!("find start", x);
-                Self::parse_remix_expr(x)
+                Self::parse_remix_expr(x, ctx)
             }
-            x => Self::into_os_env(x),
+            x => Self::lookup_env(x, ctx),
         }
     }
 
+    /// If the environment variable does not exist, the user-supplied callback is consulted.
+    fn env_fallback<'a>(ident: &str, ctx: ParseCtx) -> OsCow<'a> {
+        ctx.env_fallback
+            .and_then(|f| f(ident))
+            .and_then(crate::os_cow::into_os_cow)
+    }
+
     /// For simple rules, get the environment variables directly.
     /// For complex rules, give them to `parse_dir_rules()`.
-    pub(crate) fn handle_envs(ident: &str) -> OsCow {
+    pub(crate) fn handle_envs<'a>(ident: &'a str, ctx: ParseCtx) -> OsCow<'a> {
         use ControlFlow::{Break, Continue};
 
         match Self::get_question_mark_separator(ident) {
-            sep if sep == ' ' => var_os(ident).and_then(crate::os_cow::into_os_cow),
-            sep => match Self::parse_dir_rules(ident, Self::match_os_env, sep) {
+            ' ' => Self::lookup_env(ident, ctx),
+            sep => match Self::parse_dir_rules(
+                ident,
+                |x| Self::match_os_env(x, ctx),
+                sep,
+                ctx,
+            ) {
                 Break(x) | Continue(x) => x, // _ => None,
             },
         }
     }
+
+    /// Similar to `de()`, but when an environment variable of `$env` does not exist, `f` is consulted.
+    ///
+    /// To combine it with other settings (e.g. a timeout), use [ParseOptions::resolver()](crate::ParseOptions::resolver) instead.
+    ///
+    /// This allows "logical" locations that are not OS environment variables (e.g. `$env: my_project_root`).
+    ///
+    /// The precedence is:
+    ///
+    /// 1. The real environment variable.
+    /// 2. The callback `f`.
+    /// 3. The literal default (i.e. the raw str).
+    ///
+    /// Note: `f` receives the same name that is looked up in the environment. e.g. `$env: my-project-root` => `MY_PROJECT_ROOT`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    /// use std::{collections::HashMap, ffi::OsString};
+    ///
+    /// let map = HashMap::from([("MY_PROJECT_ROOT", "/srv/app")]);
+    ///
+    /// let path = EnvPath::from(["$env: my-project-root", "data"])
+    ///     .de_with_resolver(|name| map.get(name).map(OsString::from));
+    ///
+    /// dbg!(path.display());
+    /// ```
+    pub fn de_with_resolver<F>(self, f: F) -> Self
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        if self.raw.is_empty() {
+            return Self {
+                raw: self.raw,
+                path: None,
+            };
+        }
+
+        let path = self.raw.parse_with(ParseCtx {
+            env_fallback: Some(&f),
+            ..Default::default()
+        });
+
+        Self {
+            raw: self.raw,
+            path,
+        }
+    }
+
+    /// Similar to `de()`, but `$env` reads the environment variables from an in-memory `map`, rather than the process environment.
+    ///
+    /// To combine it with other settings (e.g. a dry run), use [ParseOptions::env_map()](crate::ParseOptions::env_map) instead.
+    ///
+    /// This is useful for sandboxing and deterministic tests. The variables that `$dir` reads (e.g. `XDG_CONFIG_HOME` of `$dir: xdg-config-home`, `CARGO_HOME` and `PATH`) also come from the map.
+    ///
+    /// Note: The keys of `map` are the names after conversion. e.g. `$env: xdg-data-home` => `XDG_DATA_HOME`, while `env * home` => `home`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    /// use std::{collections::HashMap, ffi::OsString, path::Path};
+    ///
+    /// let map = HashMap::from([("HOME".to_owned(), OsString::from("/home/m"))]);
+    ///
+    /// let path = EnvPath::from(["$env: home", "dev"]).de_with_env(&map);
+    /// assert_eq!(path.path, Some(Path::new("/home/m").join("dev")));
+    /// ```
+    pub fn de_with_env(self, map: &HashMap<String, OsString>) -> Self {
+        if self.raw.is_empty() {
+            return Self {
+                raw: self.raw,
+                path: None,
+            };
+        }
+
+        let path = self.raw.parse_with(ParseCtx {
+            env_map: Some(map),
+            ..Default::default()
+        });
+
+        Self {
+            raw: self.raw,
+            path,
+        }
+    }
+
+    /// Resolves the raw sequence, exports the result as the environment variable `var_name`, and returns it.
+    ///
+    /// Child processes spawned afterwards inherit the variable. If the raw sequence is empty, nothing is exported and None is returned.
+    ///
+    /// Note: This calls [std::env::set_var()], which is not thread-safe on some platforms (e.g. Linux). Other threads that read or write the environment at the same time (including C libraries calling `getenv`) may observe a torn value or crash. It is best to call it before spawning any threads.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$dir: cache", "envpath"]);
+    ///
+    /// if let Some(dir) = path.resolve_and_export("ENVPATH_CACHE_DIR") {
+    ///     assert_eq!(std::env::var_os("ENVPATH_CACHE_DIR"), Some(dir.into()));
+    /// }
+    /// ```
+    pub fn resolve_and_export(&self, var_name: &str) -> Option<PathBuf> {
+        if self.raw.is_empty() {
+            return None;
+        }
+
+        let path = self.raw.parse()?;
+        std::env::set_var(var_name, &path);
+        Some(path)
+    }
 }
 
 #[cfg(test)]
@@ -159,6 +317,101 @@ mod tests {
         dbg!(s.display());
     }
 
+    #[test]
+    fn env_resolver_callback() {
+        use crate::EnvPath;
+        use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+
+        let map = HashMap::from([
+            ("ENVPATH_TEST_LOGICAL_ROOT", "/srv/app"),
+            ("PATH", "/should/not/be/used"),
+        ]);
+        let resolver = |name: &str| map.get(name).map(OsString::from);
+
+        let path = EnvPath::from(["$env: envpath-test-logical-root", "data"])
+            .de_with_resolver(resolver);
+        assert_eq!(path.path, Some(PathBuf::from("/srv/app/data")));
+
+        let path = EnvPath::from(["$env: envpath_test_none ? envpath_test_logical_root"])
+            .de_with_resolver(resolver);
+        assert_eq!(path.path, Some(PathBuf::from("/srv/app")));
+
+        // The real environment variable takes precedence.
+        let path = EnvPath::from(["$env: path"]).de_with_resolver(resolver);
+        assert_eq!(path.path, std::env::var_os("PATH").map(PathBuf::from));
+
+        // Finally, the literal default is used.
+        let path = EnvPath::from(["$env: envpath_test_none"]).de_with_resolver(resolver);
+        assert_eq!(path.path, Some(PathBuf::from("$env: envpath_test_none")));
+    }
+
+    #[test]
+    fn env_from_in_memory_map() {
+        use crate::EnvPath;
+        use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+
+        // `PATH` always exists in the process environment, but must not be read.
+        let map = HashMap::from([
+            ("ENVPATH_TEST_MAP_ONLY".to_owned(), OsString::from("/srv/map")),
+            ("home".to_owned(), OsString::from("/home/map")),
+        ]);
+
+        let p = EnvPath::from(["$env: envpath-test-map-only", "a"]).de_with_env(&map);
+        assert_eq!(p.path, Some(PathBuf::from("/srv/map/a")));
+
+        let p = EnvPath::from(["$env: path"]).de_with_env(&map);
+        assert_eq!(p.path, Some(PathBuf::from("$env: path")));
+
+        let p = EnvPath::from(["$env: path ? env * home"]).de_with_env(&map);
+        assert_eq!(p.path, Some(PathBuf::from("/home/map")));
+
+        let p = EnvPath::from(["$env: path ?? envpath_test_map_only"]).de_with_env(&map);
+        assert_eq!(p.path, Some(PathBuf::from("/srv/map")));
+
+        let p = EnvPath::from_with_env(["$env: envpath_test_map_only"], &map);
+        assert_eq!(p.path, Some(PathBuf::from("/srv/map")));
+    }
+
+    #[test]
+    #[cfg(all(feature = "consts", feature = "dirs"))]
+    fn remix_uses_the_env_map() {
+        use crate::EnvPath;
+        use std::{collections::HashMap, ffi::OsString, path::PathBuf};
+
+        let map = HashMap::from([(
+            "ENVPATH_TEST_REMIX".to_owned(),
+            OsString::from("/srv/remix"),
+        )]);
+
+        for raw in [
+            "$dir: envpath-none ? env * ENVPATH_TEST_REMIX",
+            "$const: envpath-none ? env * ENVPATH_TEST_REMIX",
+            "$env: envpath_test_none ? env * ENVPATH_TEST_REMIX",
+        ] {
+            let p = EnvPath::from([raw]).de_with_env(&map);
+            assert_eq!(p.path, Some(PathBuf::from("/srv/remix")), "{raw}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "consts")]
+    fn export_resolved_path() {
+        use crate::EnvPath;
+        use std::{env, path::PathBuf};
+
+        let name = "ENVPATH_TEST_EXPORTED";
+        let path = EnvPath::from(["$const: empty", "srv", "app"]);
+
+        let exported = path.resolve_and_export(name);
+        assert_eq!(exported, Some(PathBuf::from("srv/app")));
+        assert_eq!(env::var_os(name), Some("srv/app".into()));
+
+        let p = EnvPath::from(["$env: envpath-test-exported", "logs"]).de();
+        assert_eq!(p.path, Some(PathBuf::from("srv/app/logs")));
+
+        assert_eq!(EnvPath::default().resolve_and_export(name), None);
+    }
+
     #[test]
     fn find_the_first_colon() {
         let s = "$project(com.x)";
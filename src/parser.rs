@@ -1,95 +1,280 @@
-use crate::{os_cow, EnvPath, OsCow};
-use std::path::PathBuf;
+use crate::{os_cow, EnvPath, OsCow, ParseOptions};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    ffi::{OsStr, OsString},
+    path::{is_separator, Path, PathBuf},
+};
 
 /// fullwidth colon
+#[cfg(feature = "unicode-separators")]
 pub(crate) const FULL_COLON: char = '\u{FF1A}';
 /// halfwidth colon
 pub(crate) const HALF_COLON: char = '\u{3A}';
 
 const CHUNK_NUM: usize = 2;
 
-pub(crate) fn parse<S: AsRef<str>, I: IntoIterator<Item = S>>(
+/// A user-supplied callback, which is consulted when the environment variable of `$env` does not exist.
+pub(crate) type EnvFallback<'f> = &'f dyn Fn(&str) -> Option<OsString>;
+
+/// A user-supplied callback, which is consulted for every raw chunk before the built-in resolution (see [EnvPath::de_with_overrides()]).
+pub(crate) type ChunkOverride<'f> = &'f dyn Fn(&str) -> Option<PathBuf>;
+
+/// An in-memory map of environment variables, which replaces the process environment (see [EnvPath::de_with_env()] and [ParseOptions::env_map()]).
+pub(crate) trait EnvMap {
+    fn get_var(&self, name: &str) -> Option<OsString>;
+}
+
+impl EnvMap for HashMap<String, OsString> {
+    fn get_var(&self, name: &str) -> Option<OsString> {
+        self.get(name).cloned()
+    }
+}
+
+impl EnvMap for BTreeMap<String, OsString> {
+    fn get_var(&self, name: &str) -> Option<OsString> {
+        self.get(name).cloned()
+    }
+}
+
+/// Options that are threaded through the parsing of a single `EnvPath`.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct ParseCtx<'f> {
+    pub(crate) env_fallback: Option<EnvFallback<'f>>,
+    /// If it is Some, `$env` reads the environment variables from the map, rather than the process environment.
+    pub(crate) env_map: Option<&'f dyn EnvMap>,
+    pub(crate) options: Option<&'f ParseOptions>,
+    pub(crate) overrides: Option<ChunkOverride<'f>>,
+}
+
+impl ParseCtx<'_> {
+    /// Whether `??` should skip the existence check of the path (see [ParseOptions::dry_run()]).
+    pub(crate) fn is_dry_run(&self) -> bool {
+        self.options.is_some_and(|o| o.dry_run)
+    }
+
+    /// Whether the ident of `$env` should be kept as is, rather than being uppercased (see [ParseOptions::env_case_sensitive()]).
+    pub(crate) fn is_env_case_sensitive(&self) -> bool {
+        self.options.is_some_and(|o| o.env_case_sensitive)
+    }
+}
+
+/// Parses the raw sequence into a path, taking the options of `ctx` into account.
+pub(crate) fn parse_with<S: AsRef<str>, I: IntoIterator<Item = S>>(
     iter: I,
+    ctx: ParseCtx,
 ) -> Option<PathBuf> {
+    parse_with_join(iter, ctx, join_chunk)
+}
+
+/// Similar to `parse_with()`, but every chunk (except for the deduplicated random chunks) is joined by `join`, e.g. through a [ResolverCache](crate::ResolverCache).
+pub(crate) fn parse_with_join<S, I, F>(iter: I, ctx: ParseCtx, mut join: F) -> Option<PathBuf>
+where
+    S: AsRef<str>,
+    I: IntoIterator<Item = S>,
+    F: FnMut(&Path, &str, &mut String, ParseCtx) -> Option<PathBuf>,
+{
     // Create a new string to store the casing for later use
     let mut casing = String::with_capacity(30);
 
+    // If `dedup_random` is enabled, identical random chunks (e.g. two `$val: rand-8`) share the value of the first one.
+    let mut memo = ctx
+        .options
+        .is_some_and(|o| o.dedup_random)
+        .then(HashMap::<String, Option<OsString>>::new);
+
     iter.into_iter()
         // Fold over the EnvPathRaw sequence, accumulating the PathBuf
-        .fold(Some(PathBuf::with_capacity(16)), |acc, s| {
-            acc.and_then(|acc_p| {
-                let s = s.as_ref();
-                // Split the string into chunks on colons.
-                let chunks = get_chunks(s.trim());
-
-                // Get the number of chunks
-                let len = if chunks.is_empty() { 0 } else { CHUNK_NUM };
-
-                // Define a fn to handle values. If val is None, then the default value is returned.
-                // Note: crate::os_cow::from_str(s) is the default value. `s` is the raw str.
-                fn or_default<'a>(val: OsCow<'a>, s: &'a str) -> OsCow<'a> {
-                    val.or_else(|| os_cow::from_str(s))
+        .try_fold(PathBuf::with_capacity(16), |acc_p, s| {
+            let s = s.as_ref();
+            match memo.as_mut() {
+                Some(memo) if is_random_chunk(s) => {
+                    let key = s.trim();
+                    let value = match memo.get(key) {
+                        Some(v) => v.clone(),
+                        None => {
+                            let v = resolve_chunk(s, &mut casing, ctx)
+                                .or_else(|| os_cow::from_str(s))
+                                .map(Cow::into_owned);
+                            memo.insert(key.to_owned(), v.clone());
+                            v
+                        }
+                    };
+                    value.map(|p| acc_p.join(collapse_separators(&p)))
                 }
+                _ => join(&acc_p, s, &mut casing, ctx),
+            }
+        })
+}
 
-                // When calling this closure, make sure len >= 2
-                let get_2nd_chunk = || unsafe { chunks.get_unchecked(1) };
-
-                // Match on the number of chunks
-                match len {
-                    // If the length is 0 or 1, return the default value.
-                    0 | 1 => or_default(None, s),
-                    // If the first element is $env, get the value of the environment variable with the second element as the key
-                    _ => match chunks[0] {
-                        "$env" => {
-                            match get_2nd_chunk() {
-                                x if x.contains('*') => {
-                                    casing = x.to_string();
-                                }
-                                x => {
-                                    casing = x.to_ascii_uppercase();
-                                    // Warning: The unsafe function is used here!
-                                    if casing.contains('-') {
-                                        for i in unsafe { casing.as_bytes_mut() } {
-                                            // Replace all '-' with '_'
-                                            if *i == b'-' {
-                                                *i = b'_';
-                                            }
-                                        }
-                                    }
+/// Whether the chunk is a special chunk that yields a random value (e.g. `$val: rand-8` or `$dir: tmp-rand`).
+fn is_random_chunk(s: &str) -> bool {
+    any_ident(s, is_random_ident)
+}
+
+/// Whether the `ident` of `scheme` yields a random value, i.e. `$val: rand-*` (e.g. `rand-8` or `rand-base32-8`) or `$dir: tmp-rand`.
+pub(crate) fn is_random_ident(scheme: &str, ident: &str) -> bool {
+    match scheme {
+        "$val" => ident.starts_with("rand-"),
+        "$dir" => matches!(ident, "tmp-rand" | "tmp_random"),
+        _ => false,
+    }
+}
+
+/// Calls `f` with the scheme and the ident of each alternative of a special chunk, and returns `true` if any of them does. A literal chunk is always `false`.
+///
+/// A remix expression is attributed to its own scheme, e.g. `$const: none ?? val * rand-8` => (`$const`, `none`), (`$val`, `rand-8`).
+pub(crate) fn any_ident<F: Fn(&str, &str) -> bool>(s: &str, f: F) -> bool {
+    split_alternatives(s)
+        .iter()
+        .any(|chunk| match get_chunks(chunk.trim())[..] {
+            [scheme, ident] if scheme.starts_with('$') => {
+                match ident.split_once('*') {
+                    Some((start, x)) if EnvPath::starts_with_remix_expr(ident) => {
+                        f(&format!("${}", start.trim()), x.trim())
+                    }
+                    _ => f(scheme, ident),
+                }
+            }
+            _ => false,
+        })
+}
+
+/// Whether a `?` chain of the special chunk contains `??`, i.e. the existence of a path is checked, whose result may change at runtime.
+pub(crate) fn has_existence_check(s: &str) -> bool {
+    is_special_chunk(s)
+        && normalize_separators(s)
+            .split('?')
+            .skip(1)
+            .any(|x| x.trim().is_empty())
+}
+
+/// Resolves a single raw chunk (e.g. `$env: home`) and joins it to `acc_p`.
+///
+/// If the special rule of the chunk cannot be resolved, the raw str is used as the default value.
+///
+/// `casing` is a reusable buffer for the converted name of `$env`.
+pub(crate) fn join_chunk(
+    acc_p: &Path,
+    s: &str,
+    casing: &mut String,
+    ctx: ParseCtx,
+) -> Option<PathBuf> {
+    // Note: crate::os_cow::from_str(s) is the default value. `s` is the raw str.
+    match resolve_chunk(s, casing, ctx) {
+        // Join the path of the accumulator with the parsed path.
+        Some(p) if is_special_chunk(s) => Some(acc_p.join(collapse_separators(&p))),
+        p => p
+            .or_else(|| os_cow::from_str(s))
+            .map(|p| acc_p.join(p)),
+    }
+}
+
+/// Resolves a single raw chunk without joining it.
+///
+/// A literal chunk (e.g. `dev`) is returned as is, while a special chunk (e.g. `$dir: cfg`) that cannot be resolved returns None.
+pub(crate) fn resolve_chunk<'a>(
+    s: &'a str,
+    casing: &mut String,
+    ctx: ParseCtx,
+) -> OsCow<'a> {
+    // If the override returns Some, it short-circuits the built-in resolution.
+    if let Some(p) = ctx.overrides.and_then(|f| f(s.trim())) {
+        return os_cow::into_os_cow(p);
+    }
+
+    // An escaped chunk (e.g. `\$env: home`) is always literal.
+    if let Some(x) = unescape_chunk(s) {
+        return os_cow::into_os_cow(x);
+    }
+
+    // e.g. `$env： a ? b ？ c` => `$env: a ? b ? c`
+    if let Cow::Owned(s) = normalize_separators(s) {
+        return resolve_chunk(&s, casing, ctx).map(|x| Cow::Owned(x.into_owned()));
+    }
+
+    // Split the string into chunks on colons.
+    let chunks = get_chunks(s.trim());
+
+    // Get the number of chunks
+    let len = if chunks.is_empty() { 0 } else { CHUNK_NUM };
+
+    // When calling this closure, make sure len >= 2
+    let get_2nd_chunk = || unsafe { chunks.get_unchecked(1) };
+
+    // Match on the number of chunks
+    match len {
+        // If the length is 0 or 1, it is a literal chunk.
+        0 | 1 => os_cow::from_str(s),
+        // e.g. `$const: exe_suffix ? $env: home`
+        _ if chunks[0].starts_with('$') && is_cross_scheme(get_2nd_chunk()) => {
+            resolve_cross_scheme(chunks[0], get_2nd_chunk(), ctx)
+        }
+        // If the first element is $env, get the value of the environment variable with the second element as the key
+        _ => match chunks[0] {
+            "$env" => {
+                // e.g. `home :- /tmp/fallback` => (`home`, Some(`/tmp/fallback`), false)
+                let (ident, inline_default, assign) =
+                    split_env_default(get_2nd_chunk());
+                // e.g. `user | lower` => (`user`, Some(EnvFilter::Lower))
+                let (ident, filter) = split_env_filter(ident);
+
+                match ident {
+                    // With `env_case_sensitive`, the ident is used exactly as written.
+                    x if x.contains('*') || ctx.is_env_case_sensitive() => {
+                        *casing = x.to_string();
+                    }
+                    x => {
+                        *casing = x.to_ascii_uppercase();
+                        // Warning: The unsafe function is used here!
+                        if casing.contains('-') {
+                            for i in unsafe { casing.as_bytes_mut() } {
+                                // Replace all '-' with '_'
+                                if *i == b'-' {
+                                    *i = b'_';
                                 }
                             }
-
-                            // handle_env: Parsing environment variables (e.g.: `$env: home` or `$env: userprofile ?? home`)
-                            or_default(EnvPath::handle_envs(&casing), s)
-                        }
-                        // If the first element is $const and the consts feature is enabled, get the value of the directory with the second element as the key
-                        #[cfg(feature = "consts")]
-                        "$const" => {
-                            or_default(EnvPath::handle_consts(get_2nd_chunk()), s)
-                        }
-                        #[cfg(feature = "value")]
-                        "$val" => {
-                            or_default(EnvPath::handle_values(get_2nd_chunk()), s)
-                        }
-                        // If the first element is $dir and the base-dirs feature is enabled, get the value of the base directory with the second element as the key
-                        #[cfg(feature = "dirs")]
-                        "$dir" => {
-                            or_default(EnvPath::handle_dirs(get_2nd_chunk()), s)
                         }
-                        // If the first element starts with `$proj` and the `project` feature is enabled, get the value of the project directory with the second element as the key
-                        #[cfg(feature = "project")]
-                        x if x.starts_with("$proj") => or_default(
-                            EnvPath::handle_project_dirs(x, get_2nd_chunk()),
-                            s,
-                        ),
-                        // If none of the above conditions are met, return the default value.
-                        _ => or_default(None, s),
-                    },
+                    }
                 }
-                // Join the path of the accumulator with the parsed path.
-                .map(|p| acc_p.join(p))
-            })
-        })
+
+                // handle_env: Parsing environment variables (e.g.: `$env: home` or `$env: userprofile ?? home`)
+                // If all of them fail, the inline default value (if any) is used before the raw str.
+                // The value of `$env` is always owned, so it does not need to borrow `casing`.
+                // With `:=`, the default value may also be assigned to the variable.
+                // The filter (if any) only applies to the value of the variable, not to the literal default.
+                EnvPath::handle_envs(casing, ctx)
+                    .map(|x| match filter {
+                        Some(f) => f.apply(&x),
+                        _ => Cow::Owned(x.into_owned()),
+                    })
+                    .or_else(|| {
+                        let default = inline_default?;
+                        if assign {
+                            assign_env_default(casing, default, ctx)
+                        }
+                        os_cow::from_str(default)
+                    })
+            }
+            // If the first element is $const and the consts feature is enabled, get the value of the directory with the second element as the key
+            #[cfg(feature = "consts")]
+            "$const" => EnvPath::handle_consts(get_2nd_chunk(), ctx),
+            #[cfg(feature = "value")]
+            "$val" => EnvPath::handle_values(get_2nd_chunk(), ctx),
+            // If the first element is $dir and the base-dirs feature is enabled, get the value of the base directory with the second element as the key
+            #[cfg(feature = "dirs")]
+            "$dir" => EnvPath::handle_dirs(get_2nd_chunk(), ctx),
+            // If the first element starts with `$proj` and the `project` feature is enabled, get the value of the project directory with the second element as the key
+            #[cfg(feature = "project")]
+            x if x.starts_with("$proj") => {
+                EnvPath::handle_project_dirs(x, get_2nd_chunk(), ctx)
+            }
+            // An unknown (or disabled) scheme cannot be resolved.
+            x if x.starts_with('$') => None,
+            // If none of the above conditions are met, it is a literal chunk (e.g. `C:\Users`).
+            _ => os_cow::from_str(s),
+        },
+    }
 }
 
 impl EnvPath<'_> {
@@ -124,15 +309,183 @@ impl EnvPath<'_> {
             path,
         }
     }
+
+    /// Similar to `de()`, but `f` is consulted for every raw chunk (trimmed, e.g. `$dir: cfg`) before the built-in resolution. If it returns Some, that path is used for the chunk.
+    ///
+    /// To combine it with other settings, use [ParseOptions::overrides()] instead.
+    ///
+    /// This lets you redirect specific chunks inline, e.g. in tests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    /// use std::path::PathBuf;
+    ///
+    /// let path = EnvPath::from(["$dir: cfg", "app"]).de_with_overrides(|chunk| match chunk {
+    ///     "$dir: cfg" => Some(PathBuf::from("/srv/cfg")),
+    ///     _ => None,
+    /// });
+    ///
+    /// assert_eq!(path.path, Some(PathBuf::from("/srv/cfg/app")));
+    /// ```
+    pub fn de_with_overrides<F>(self, f: F) -> Self
+    where
+        F: Fn(&str) -> Option<PathBuf>,
+    {
+        if self.raw.is_empty() {
+            return Self {
+                raw: self.raw,
+                path: None,
+            };
+        }
+
+        let path = self.raw.parse_with(ParseCtx {
+            overrides: Some(&f),
+            ..Default::default()
+        });
+
+        Self {
+            raw: self.raw,
+            path,
+        }
+    }
+
+    /// Resolves a single raw chunk into a path, without constructing an `EnvPath`.
+    ///
+    /// It is the same as `EnvPath::new([s]).path`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let cfg = EnvPath::parse_str("$dir: cfg");
+    /// dbg!(&cfg);
+    ///
+    /// assert_eq!(EnvPath::parse_str("$dir: cfg"), EnvPath::new(["$dir: cfg"]).path);
+    ///
+    /// # #[cfg(feature = "consts")]
+    /// assert_eq!(EnvPath::parse_str("$const: empty ?? os"), Some(std::env::consts::OS.into()));
+    /// ```
+    pub fn parse_str(s: &str) -> Option<PathBuf> {
+        parse_with([s], ParseCtx::default())
+    }
+
+    /// Similar to `de()`, but returns [ParseError::Unresolved](crate::ParseError::Unresolved) if there is nothing to resolve (i.e. the raw sequence is empty).
+    ///
+    /// See also [try_de_with_options()](EnvPath::try_de_with_options).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$dir: cfg", "app"]).try_de();
+    /// assert!(path.is_ok());
+    ///
+    /// assert!(EnvPath::default().try_de().is_err());
+    /// ```
+    pub fn try_de(self) -> Result<Self, crate::ParseError> {
+        self.try_de_with_options(&ParseOptions::default())
+    }
+
+    /// Resolves each raw chunk separately and yields the results in order, without joining them.
+    ///
+    /// A literal chunk (e.g. `app`) is yielded as is, while a special chunk that cannot be resolved yields `None` (unlike `de()`, the raw str is not used as the default value). This lets you see exactly which chunk failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$dir: cfg", "app", "$env: envpath_unknown_var"]);
+    ///
+    /// for (raw, seg) in path.get_raw().iter().zip(path.resolved_segments()) {
+    ///     println!("{raw} => {seg:?}");
+    /// }
+    ///
+    /// let segments = path.resolved_segments().collect::<Vec<_>>();
+    /// assert_eq!(segments[1].as_deref(), Some("app".as_ref()));
+    /// assert_eq!(segments[2], None);
+    /// ```
+    pub fn resolved_segments(&self) -> impl Iterator<Item = OsCow<'_>> {
+        let mut casing = String::with_capacity(30);
+
+        self.raw
+            .iter()
+            .map(move |s| resolve_chunk(s, &mut casing, ParseCtx::default()))
+    }
+}
+
+/// Whether a `?`/`??` chain contains a segment with its own scheme (e.g. `exe_suffix ? $env: home`).
+fn is_cross_scheme(ident: &str) -> bool {
+    match EnvPath::get_question_mark_separator(ident) {
+        ' ' => false,
+        sep => ident
+            .split(sep)
+            .any(is_special_chunk),
+    }
+}
+
+/// Resolves a chain whose segments carry their own scheme, e.g. `$const: exe_suffix ? $env: home ? xdg-data-home`.
+///
+/// A segment without a scheme (e.g. `xdg-data-home`) inherits the leading `scheme` of the chunk (e.g. `$const`).
+fn resolve_cross_scheme<'a>(scheme: &str, ident: &str, ctx: ParseCtx) -> OsCow<'a> {
+    use std::ops::ControlFlow::{Break, Continue};
+
+    let sep = EnvPath::get_question_mark_separator(ident);
+
+    let rules = EnvPath::parse_dir_rules(
+        ident,
+        |x| {
+            let chunk = match is_special_chunk(x) {
+                true => Cow::Borrowed(x),
+                _ => Cow::Owned(format!("{scheme}: {x}")),
+            };
+            resolve_chunk(&chunk, &mut String::new(), ctx)
+                .map(|p| Cow::Owned(p.into_owned()))
+        },
+        sep,
+        ctx,
+    );
+
+    match rules {
+        Break(x) | Continue(x) => x.map(|p| Cow::Owned(p.into_owned())),
+    }
+}
+
+/// The colons that separate the scheme (e.g. `$env`) from the ident.
+#[cfg(feature = "unicode-separators")]
+pub(crate) const COLONS: [char; 2] = [HALF_COLON, FULL_COLON];
+/// The colons that separate the scheme (e.g. `$env`) from the ident.
+#[cfg(all(
+    not(feature = "unicode-separators"),
+    any(feature = "consts", feature = "project")
+))]
+pub(crate) const COLONS: [char; 1] = [HALF_COLON];
+
+/// Split the string into chunks on colons.
+/// Half and full colons are matched here.
+/// If someone forgets to switch the Chinese input method to English, it is easy to type ':' as '：', the two characters are particularly similar. To solve the confusion problem, it supports both.
+///
+/// If the `unicode-separators` feature is disabled, only the half colon is matched.
+#[cfg(not(feature = "unicode-separators"))]
+pub(crate) fn get_chunks(s: &str) -> Vec<&str> {
+    match find_colon(s, HALF_COLON) {
+        Some(_) => split_n(s, HALF_COLON),
+        _ => Vec::new(),
+    }
 }
 
 /// Split the string into chunks on colons.
 /// Half and full colons are matched here.
 /// If someone forgets to switch the Chinese input method to English, it is easy to type ':' as '：', the two characters are particularly similar. To solve the confusion problem, it supports both.
+#[cfg(feature = "unicode-separators")]
 pub(crate) fn get_chunks(s: &str) -> Vec<&str> {
     let hc = HALF_COLON;
     let fc = FULL_COLON;
-    match (s.find(hc), s.find(fc)) {
+    match (find_colon(s, hc), find_colon(s, fc)) {
         (Some(h), Some(f)) if h < f => split_n(s, hc),
         (Some(h), Some(f)) if f < h => split_n(s, fc),
         (Some(_), None) => split_n(s, hc),
@@ -141,12 +494,359 @@ pub(crate) fn get_chunks(s: &str) -> Vec<&str> {
     }
 }
 
-fn split_n(s: &str, c: char) -> Vec<&str> {
-    s.splitn(CHUNK_NUM, c)
-        .map(|x| x.trim())
+/// Converts the fullwidth colon (`：`) and question mark (`？`) of a special chunk to ASCII up front, so that the parser only deals with `:` and `?`, even if both kinds are mixed in one chunk (e.g. `$const: none ？ os ? arch`).
+///
+/// Only a chunk that starts with a `$` scheme is converted, since a fullwidth char of a literal chunk (e.g. `a：b`) may be a part of a file name on purpose. For the same reason, the inline default of `$env` (e.g. `:- /tmp/a：b`) is left as is.
+#[cfg(feature = "unicode-separators")]
+pub(crate) fn normalize_separators(s: &str) -> Cow<'_, str> {
+    use crate::os_env::{FWQM, HWQM};
+
+    let end = [ENV_DEFAULT_MARKER, ENV_ASSIGN_MARKER]
+        .iter()
+        .filter_map(|m| s.find(m))
+        .min()
+        .unwrap_or(s.len());
+    let (head, tail) = s.split_at(end);
+
+    if !head.trim_start().starts_with('$')
+        || !head.contains(COLONS)
+        || !head.contains([FULL_COLON, FWQM])
+    {
+        return Cow::Borrowed(s);
+    }
+
+    let mut out = head
+        .chars()
+        .map(|c| match c {
+            FULL_COLON => HALF_COLON,
+            FWQM => HWQM,
+            c => c,
+        })
+        .collect::<String>();
+    out.push_str(tail);
+
+    Cow::Owned(out)
+}
+
+/// If the `unicode-separators` feature is disabled, the fullwidth chars are always literal.
+#[cfg(not(feature = "unicode-separators"))]
+pub(crate) fn normalize_separators(s: &str) -> Cow<'_, str> {
+    Cow::Borrowed(s)
+}
+
+/// The escape character of the raw chunk, e.g. `\$env: home` is a literal `$env: home`, and `foo\:bar` is a literal `foo:bar`.
+const ESCAPE: char = '\\';
+
+/// Strips one backslash from `\$`, `\:` and `\\` of an escaped chunk, and returns None if the chunk is not escaped.
+///
+/// A chunk is escaped if it starts with `\$` (e.g. `\$env: home`), or if it is a literal chunk that contains `\:` (e.g. `foo\:bar`).
+/// Other chunks are left as is, so that the backslashes of Windows paths (e.g. `C:\$Recycle.Bin` or `\\srv\$share`) do not need to be escaped.
+fn unescape_chunk(s: &str) -> Option<String> {
+    let is_colon = |c: char| {
+        c == HALF_COLON || (cfg!(feature = "unicode-separators") && c == '\u{FF1A}')
+    };
+    let is_escapable = |c: char| c == '$' || c == ESCAPE || is_colon(c);
+
+    let trimmed = s.trim_start();
+    let escaped = match trimmed.strip_prefix(ESCAPE) {
+        Some(x) if x.starts_with('$') => true,
+        _ => {
+            !trimmed.starts_with('$')
+                && s.match_indices(ESCAPE)
+                    .any(|(i, _)| s[i + 1..].starts_with(is_colon))
+        }
+    };
+    if !escaped {
+        return None;
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut chars = trimmed.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(&n) if c == ESCAPE && is_escapable(n) => {
+                out.push(n);
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// The reverse of `unescape_chunk()`, i.e. escapes a literal chunk (e.g. the path `$Recycle.Bin`), so that it is neither resolved nor unescaped.
+///
+/// A chunk that does not need it is borrowed as is.
+pub(crate) fn escape_chunk(s: &str) -> Cow<'_, str> {
+    if !is_special_chunk(s) && unescape_chunk(s).is_none() {
+        return Cow::Borrowed(s);
+    }
+
+    // Only the leading `$` and the backslashes need it, the rest (e.g. `:`) is literal in an escaped chunk.
+    let trimmed = s.trim_start();
+    let mut out = String::with_capacity(s.len() + 4);
+    out.push_str(&s[..s.len() - trimmed.len()]);
+
+    if trimmed.starts_with('$') {
+        out.push(ESCAPE)
+    }
+    for c in trimmed.chars() {
+        if c == ESCAPE {
+            out.push(ESCAPE)
+        }
+        out.push(c)
+    }
+    Cow::Owned(out)
+}
+
+/// The marker of the inline default value in `$env`, similar to `${VAR:-default}` of the shell.
+const ENV_DEFAULT_MARKER: &str = ":-";
+/// Similar to `ENV_DEFAULT_MARKER`, but the default value is also assigned to the variable, like `${VAR:=default}` of the shell.
+const ENV_ASSIGN_MARKER: &str = ":=";
+
+/// Splits the ident of `$env` (e.g. `home ? userprofile :- /tmp/fallback`) into the expression and the inline default value.
+///
+/// The bool is true if the marker is `:=` (i.e. default-and-assign).
+///
+/// The default value is literal only, so a default that is itself a `$`-expression is rejected (i.e. treated as if there were no default).
+fn split_env_default(s: &str) -> (&str, Option<&str>, bool) {
+    let split = |marker| s.split_once(marker);
+
+    match split(ENV_DEFAULT_MARKER)
+        .map(|x| (x, false))
+        .or_else(|| split(ENV_ASSIGN_MARKER).map(|x| (x, true)))
+    {
+        Some(((expr, default), assign)) => (
+            expr.trim(),
+            Some(default.trim()).filter(|x| !x.starts_with('$')),
+            assign,
+        ),
+        _ => (s, None, false),
+    }
+}
+
+/// The post-filter of `$env` (e.g. `$env: user | lower`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EnvFilter {
+    Lower,
+    Upper,
+}
+
+impl EnvFilter {
+    /// Converts the case of the value. A non-UTF-8 value is returned unchanged.
+    fn apply<'a>(self, value: &OsStr) -> Cow<'a, OsStr> {
+        let converted = value.to_str().map(|s| match self {
+            Self::Lower => s.to_lowercase(),
+            Self::Upper => s.to_uppercase(),
+        });
+
+        match converted {
+            Some(s) => Cow::Owned(s.into()),
+            _ => Cow::Owned(value.to_owned()),
+        }
+    }
+}
+
+/// Splits the ident of `$env` (e.g. `user ? logname | lower`) into the expression and the post-filter.
+///
+/// An unknown filter is not split, so it remains a part of the expression.
+fn split_env_filter(s: &str) -> (&str, Option<EnvFilter>) {
+    let Some((expr, filter)) = s.rsplit_once('|') else {
+        return (s, None);
+    };
+
+    let filter = match filter.trim() {
+        x if x.eq_ignore_ascii_case("lower") => EnvFilter::Lower,
+        x if x.eq_ignore_ascii_case("upper") => EnvFilter::Upper,
+        _ => return (s, None),
+    };
+
+    (expr.trim(), Some(filter))
+}
+
+/// Assigns the default value of `$env: name := default` to the environment variable `name`.
+///
+/// It only takes effect if [ParseOptions::allow_env_mutation()] is set, and the expression is a single variable (not a `?` chain or `env *`).
+fn assign_env_default(name: &str, default: &str, ctx: ParseCtx) {
+    let allowed = ctx
+        .options
+        .is_some_and(|o| o.allow_env_mutation);
+
+    if allowed
+        && !name.is_empty()
+        && !name.contains(['*', '=', '\0'])
+        && EnvPath::get_question_mark_separator(name) == ' '
+    {
+        std::env::set_var(name, default);
+
+        // The environment has changed.
+        #[cfg(feature = "cache")]
+        crate::global_cache::refresh();
+    }
+}
+
+/// Returns `true` if the raw chunk has a special rule (e.g. `$env: home`, `$proj(com.x.y): data`), rather than being a literal path segment.
+pub(crate) fn is_special_chunk(s: &str) -> bool {
+    get_chunks(s.trim())
+        .first()
+        .is_some_and(|x| x.starts_with('$'))
+}
+
+/// Whether the scheme (e.g. `$dir` or `$proj(com.x.y)`) is known, and its feature is enabled.
+pub(crate) fn is_known_scheme(scheme: &str) -> bool {
+    match scheme {
+        "$env" => true,
+        "$const" => cfg!(feature = "consts"),
+        "$val" => cfg!(feature = "value"),
+        "$dir" => cfg!(feature = "dirs"),
+        x if x.starts_with("$proj") => cfg!(feature = "project"),
+        _ => false,
+    }
+}
+
+/// Splits a `?`/`??` chain into standalone chunks, one per alternative, e.g. `$env: a ?? b` => `["$env: a", "$env: b"]`.
+///
+/// As in `resolve_cross_scheme()`, an alternative without a scheme inherits the scheme of the chunk. For `$proj`, it inherits the last project name (e.g. `$proj(a.b): cfg ? (x.y): data ? cache` => `$proj(x.y): cache`).
+///
+/// A chunk without a chain is returned as is.
+pub(crate) fn split_alternatives(s: &str) -> Vec<Cow<'_, str>> {
+    if let Cow::Owned(s) = normalize_separators(s) {
+        return split_alternatives(&s)
+            .into_iter()
+            .map(|x| Cow::Owned(x.into_owned()))
+            .collect();
+    }
+
+    let (mut scheme, ident) = match get_chunks(s.trim())[..] {
+        [scheme, ident] if scheme.starts_with('$') => (Cow::Borrowed(scheme), ident),
+        _ => return vec![Cow::Borrowed(s)],
+    };
+
+    let sep = match EnvPath::get_question_mark_separator(ident) {
+        ' ' => return vec![Cow::Borrowed(s)],
+        sep => sep,
+    };
+
+    ident
+        .split(sep)
+        .map(str::trim)
+        .filter(|x| !x.is_empty())
+        .map(|x| match x {
+            x if is_special_chunk(x) => Cow::Borrowed(x),
+            x if scheme.starts_with("$proj") && x.starts_with('(') => {
+                let chunk = format!("$proj{x}");
+                if let Some(name) = get_chunks(&chunk).first() {
+                    scheme = Cow::Owned(name.to_string());
+                }
+                Cow::Owned(chunk)
+            }
+            x => Cow::Owned(format!("{scheme}: {x}")),
+        })
         .collect()
 }
 
+/// Splits a whole path string (e.g. `$dir: cfg/app/config.ron`) into raw chunks on the separators of the OS (`/`, and also `\\` on Windows), e.g. `["$dir: cfg", "app", "config.ron"]`.
+///
+/// - The root (e.g. `/` or `C:\\`) is kept as the first chunk.
+/// - A separator inside parentheses (e.g. `$proj(...)`) is not split.
+/// - The inline default of `$env` (e.g. `$env: home :- /tmp/a`) is literal, so the rest of the string belongs to that chunk.
+/// - Empty chunks (e.g. of `a//b`) are skipped.
+pub(crate) fn split_path_str(s: &str) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let (mut start, mut depth) = (0, 0usize);
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            c if is_separator(c) && depth == 0 => {
+                let chunk = &s[start..i];
+                if chunk.trim_start().starts_with('$')
+                    && (chunk.contains(ENV_DEFAULT_MARKER) || chunk.contains(ENV_ASSIGN_MARKER))
+                {
+                    break;
+                }
+
+                match chunk.trim() {
+                    // e.g. `/` or `C:\`
+                    x if chunks.is_empty()
+                        && (x.is_empty() || x.ends_with(':'))
+                        && !x.starts_with('$') =>
+                    {
+                        chunks.push(&s[start..i + c.len_utf8()])
+                    }
+                    "" => {}
+                    _ => chunks.push(chunk),
+                }
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+
+    if !s[start..].trim().is_empty() {
+        chunks.push(&s[start..])
+    }
+    chunks
+}
+
+/// Finds the first colon `c` that is not inside parentheses, so that `$proj(com.$env: org.app): data` is split after `)`.
+fn find_colon(s: &str, c: char) -> Option<usize> {
+    let mut depth = 0usize;
+
+    s.char_indices()
+        .find(|&(_, x)| {
+            match x {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            x == c && depth == 0
+        })
+        .map(|(i, _)| i)
+}
+
+fn split_n(s: &str, c: char) -> Vec<&str> {
+    match find_colon(s, c) {
+        Some(i) => [&s[..i], &s[i + c.len_utf8()..]]
+            .map(str::trim)
+            .to_vec(),
+        _ => vec![s.trim()],
+    }
+}
+
+/// Collapses the doubled separators in the resolved value of a special chunk before it is joined.
+///
+/// e.g. If `$env: home` is `//` or `/home//m`, the result of joining `foo` should be `/foo` or `/home/m/foo`, rather than a path containing doubled separators.
+/// Everything else (e.g. `.` and a trailing separator) is kept as is, and on Windows, the leading `\\` of a UNC path (e.g. `\\server\share`) is kept as well.
+pub(crate) fn collapse_separators(p: &OsStr) -> Cow<'_, OsStr> {
+    let Some(s) = p.to_str() else {
+        return Cow::Borrowed(p);
+    };
+
+    // The separators before `start` are never collapsed, i.e. the leading `\\` of UNC.
+    let start = match s.as_bytes() {
+        [a, b, ..] if cfg!(windows) && is_separator(*a as char) && is_separator(*b as char) => 2,
+        _ => 0,
+    };
+
+    let mut out = String::with_capacity(s.len());
+    let mut prev_sep = false;
+    for (i, c) in s.char_indices() {
+        let sep = is_separator(c);
+        if !(sep && prev_sep && i >= start) {
+            out.push(c)
+        }
+        prev_sep = sep;
+    }
+
+    match out.len() == s.len() {
+        true => Cow::Borrowed(p),
+        _ => Cow::Owned(out.into()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::EnvPath;
@@ -163,4 +863,332 @@ mod tests {
         let path = EnvPath::from(["$env: home"]).de();
         dbg!(path.display(), path.exists());
     }
+
+    #[test]
+    #[cfg(unix)]
+    fn join_after_root_or_trailing_separator() {
+        use std::{collections::HashMap, ffi::OsString};
+
+        let de = |val: &str, raw: &[&str]| {
+            let map = HashMap::from([("ENVPATH_TEST_JOIN_ROOT".to_owned(), OsString::from(val))]);
+            EnvPath::from(raw)
+                .de_with_env(&map)
+                .display()
+                .to_string()
+        };
+        let join = |val: &str| de(val, &["$env: envpath_test_join_root", "foo"]);
+
+        assert_eq!(join("/"), "/foo");
+        assert_eq!(join("//"), "/foo");
+        assert_eq!(join("/home/m/"), "/home/m/foo");
+        assert_eq!(join("/home//m"), "/home/m/foo");
+        assert_eq!(join("/home/./m"), "/home/./m/foo");
+
+        // A lone chunk keeps its trailing separator.
+        assert_eq!(de("/home/m/", &["$env: envpath_test_join_root"]), "/home/m/");
+
+        // Literal chunks are untouched.
+        assert_eq!(EnvPath::new(["a/./b/", "c"]).display().to_string(), "a/./b/c");
+        assert_eq!(EnvPath::new(["a//b", "c"]).display().to_string(), "a//b/c");
+    }
+
+    #[test]
+    fn collapse_doubled_separators() {
+        use super::collapse_separators;
+        use std::ffi::OsStr;
+
+        let collapse = |s: &str| collapse_separators(OsStr::new(s)).into_owned();
+
+        assert_eq!(collapse("/a//b///c/"), "/a/b/c/");
+        assert_eq!(collapse("./a"), "./a");
+        assert_eq!(collapse(""), "");
+
+        #[cfg(windows)]
+        assert_eq!(collapse(r"\\server\\share"), r"\\server\share");
+    }
+
+    #[test]
+    #[cfg(feature = "unicode-separators")]
+    fn fullwidth_separators() {
+        let p = EnvPath::new(["$const： none ？ os"]);
+        assert_eq!(p.to_str(), Some(std::env::consts::OS));
+    }
+
+    #[test]
+    #[cfg(all(feature = "unicode-separators", feature = "consts"))]
+    fn normalize_mixed_separators() {
+        use super::{normalize_separators, split_alternatives};
+        use std::borrow::Cow;
+
+        assert_eq!(
+            normalize_separators("$env： a ？ b ?? c"),
+            "$env: a ? b ?? c"
+        );
+        // Literal chunks and inline defaults are left as is.
+        assert!(matches!(normalize_separators("a：b？"), Cow::Borrowed(_)));
+        assert!(matches!(normalize_separators("$a？b"), Cow::Borrowed(_)));
+        assert_eq!(
+            normalize_separators("$env： x :- /tmp/a：b？"),
+            "$env: x :- /tmp/a：b？"
+        );
+
+        let de = |raw| EnvPath::new([raw]).to_str().map(String::from);
+        assert_eq!(de("$const: none ？ os ? arch").as_deref(), Some(std::env::consts::OS));
+        assert_eq!(de("$const： none ? os").as_deref(), Some(std::env::consts::OS));
+        assert_eq!(de("a：b？").as_deref(), Some("a：b？"));
+        assert_eq!(
+            de("$env： envpath_test_unset :- /tmp/a：b").as_deref(),
+            Some("/tmp/a：b")
+        );
+        assert_eq!(
+            split_alternatives("$const： none ？ os ? arch"),
+            ["$const: none", "$const: os", "$const: arch"]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "dirs")]
+    fn overrides_short_circuit() {
+        use std::{cell::Cell, path::PathBuf};
+
+        let calls = Cell::new(0);
+        let path = EnvPath::from(["$dir: cache", "$dir: none_such", "app"]).de_with_overrides(
+            |chunk| {
+                calls.set(calls.get() + 1);
+                match chunk {
+                    "$dir: cache" => Some(PathBuf::from("/mock/cache")),
+                    _ => None,
+                }
+            },
+        );
+        assert_eq!(calls.get(), 3);
+        assert_eq!(
+            path.path,
+            Some(PathBuf::from("/mock/cache/$dir: none_such/app"))
+        );
+
+        // The chunks that are not overridden are resolved as usual.
+        let path = EnvPath::from(["$dir: cfg"]).de_with_overrides(|_| None);
+        assert_eq!(path.path, EnvPath::new(["$dir: cfg"]).path);
+    }
+
+    #[test]
+    #[cfg(not(feature = "unicode-separators"))]
+    fn fullwidth_separators_are_literal() {
+        let p = EnvPath::new(["$const： none ？ os"]);
+        assert_eq!(p.to_str(), Some("$const： none ？ os"));
+
+        let p = EnvPath::new(["$const: none ？ os"]);
+        assert_eq!(p.to_str(), Some("$const: none ？ os"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn env_inline_default() {
+        use std::env;
+
+        env::set_var("ENVPATH_TEST_INLINE_DEFAULT", "/srv/set");
+
+        let de = |raw: &str| EnvPath::new([raw, "a"]).display().to_string();
+
+        // set
+        assert_eq!(de("$env: envpath-test-inline-default :- /tmp/fallback"), "/srv/set/a");
+        // unset, with default
+        assert_eq!(de("$env: envpath_test_unset :- /tmp/fallback"), "/tmp/fallback/a");
+        assert_eq!(
+            de("$env: envpath_test_unset ? envpath_test_unset2 :- /tmp/fall back"),
+            "/tmp/fall back/a"
+        );
+        // unset, without default
+        assert_eq!(de("$env: envpath_test_unset"), "$env: envpath_test_unset/a");
+        // A `$`-expression is not allowed as the default value.
+        assert_eq!(
+            de("$env: envpath_test_unset :- $dir: cfg"),
+            "$env: envpath_test_unset :- $dir: cfg/a"
+        );
+
+        env::remove_var("ENVPATH_TEST_INLINE_DEFAULT");
+    }
+
+    #[test]
+    fn env_case_filter() {
+        use std::env;
+
+        env::set_var("ENVPATH_TEST_FILTER", "MiXeD-Case");
+
+        let de = |raw: &str| EnvPath::new([raw]).display().to_string();
+
+        assert_eq!(de("$env: envpath-test-filter | lower"), "mixed-case");
+        assert_eq!(de("$env: envpath-test-filter|UPPER"), "MIXED-CASE");
+        assert_eq!(de("$env: envpath_test_unset ? envpath-test-filter | lower"), "mixed-case");
+        // The filter does not apply to the literal default.
+        assert_eq!(de("$env: envpath_test_unset | lower :- /Tmp"), "/Tmp");
+        // An unknown filter is a part of the name.
+        assert_eq!(de("$env: envpath-test-filter | title"), "$env: envpath-test-filter | title");
+
+        env::remove_var("ENVPATH_TEST_FILTER");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn env_case_filter_non_utf8() {
+        use super::EnvFilter;
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let value = OsStr::from_bytes(b"ABC\xff");
+        assert_eq!(EnvFilter::Lower.apply(value), value);
+    }
+
+    #[test]
+    #[cfg(feature = "consts")]
+    fn resolved_segments_in_order() {
+        use std::ffi::OsStr;
+
+        let path = EnvPath::from([
+            "$const: empty",
+            "app",
+            "$env: envpath_test_never_set",
+            "$unknown: x",
+            "$const: empty ?? os",
+        ]);
+        let segments = path.resolved_segments().collect::<Vec<_>>();
+
+        assert_eq!(segments.len(), 5);
+        assert_eq!(segments[0].as_deref(), Some(OsStr::new("")));
+        assert_eq!(segments[1].as_deref(), Some(OsStr::new("app")));
+        assert_eq!(segments[2], None);
+        assert_eq!(segments[3], None);
+        assert_eq!(
+            segments[4].as_deref(),
+            Some(OsStr::new(std::env::consts::OS))
+        );
+    }
+
+    #[test]
+    fn random_chunks() {
+        for s in [
+            "$val: rand-8",
+            " $val : rand-base32-8 ",
+            "$val: rand-16:upper",
+            "$dir: tmp-rand",
+            "$dir: cfg ? tmp_random",
+            "$const: none ?? val * rand-8",
+        ] {
+            assert!(super::is_random_chunk(s), "{s}");
+        }
+
+        for s in [
+            "$env: random_var",
+            "$env: brand_home",
+            "$dir: cfg ? grandparent",
+            "$val: epoch",
+            "$val: random",
+            "rand-8",
+            "$proj(com.rand.x): data",
+        ] {
+            assert!(!super::is_random_chunk(s), "{s}");
+        }
+    }
+
+    #[test]
+    fn escaped_chunks() {
+        let raw = [r"\$env", r"foo\:bar", r"\$dir: cfg \ x", r"\$env: a\\b\:c"];
+        let path = EnvPath::new(raw);
+
+        // `get_raw()` keeps the escaped form, while the resolved path has the literal.
+        assert_eq!(path.get_raw().iter().collect::<Vec<_>>(), raw);
+        assert_eq!(
+            path.path,
+            Some(["$env", "foo:bar", r"$dir: cfg \ x", r"$env: a\b:c"].iter().collect())
+        );
+
+        // `\\` is a literal backslash in an escaped chunk.
+        assert_eq!(EnvPath::new([r"foo\\\:bar"]).path, Some(r"foo\:bar".into()));
+
+        // Other chunks are not escaped, so the backslashes of Windows paths are kept.
+        for raw in [r"C:\$Recycle.Bin", r"\\srv\$share", r"\\server\share", r"C:\a\\b"] {
+            assert_eq!(EnvPath::new([raw]).path, Some(raw.into()), "{raw}");
+        }
+        assert_eq!(
+            EnvPath::new([r"$env: envpath_test_none :- C:\$Recycle.Bin"]).path,
+            Some(r"C:\$Recycle.Bin".into())
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "consts")]
+    fn cross_scheme_chain() {
+        use std::{env, path::PathBuf};
+
+        env::set_var("ENVPATH_TEST_CROSS_SCHEME", "/srv/cross");
+
+        // `exe_extension` is empty on unix, and `exe` on windows.
+        let p = EnvPath::new(["$const: none ? $env: envpath-test-cross-scheme"]);
+        assert_eq!(p.path, Some(PathBuf::from("/srv/cross")));
+
+        let p = EnvPath::new(["$const: exe_extension ? $env: HOME"]);
+        assert_eq!(p.path, Some(PathBuf::from(env::consts::EXE_EXTENSION)));
+
+        // `os` inherits `$const`, rather than `$env`.
+        let p = EnvPath::new(["$const: none ? $env: envpath-test-none ? os"]);
+        assert_eq!(p.path, Some(PathBuf::from(env::consts::OS)));
+
+        let p = EnvPath::new(["$env: envpath-test-none ? $const: family"]);
+        assert_eq!(p.path, Some(PathBuf::from(env::consts::FAMILY)));
+
+        // All segments share the same scheme.
+        let p = EnvPath::new(["$const: none ? os ? family"]);
+        assert_eq!(p.path, Some(PathBuf::from(env::consts::OS)));
+
+        let p = EnvPath::new(["$const: none ? env * ENVPATH_TEST_CROSS_SCHEME"]);
+        assert_eq!(p.path, Some(PathBuf::from("/srv/cross")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn env_default_and_assign() {
+        use crate::ParseOptions;
+        use std::env;
+
+        let raw = ["$env: envpath-test-assign := /opt/app", "a"];
+        let name = "ENVPATH_TEST_ASSIGN";
+
+        // Without `allow_env_mutation`, `:=` is the same as `:-`.
+        let p = EnvPath::from(raw).de();
+        assert_eq!(p.to_str(), Some("/opt/app/a"));
+        assert_eq!(env::var_os(name), None);
+
+        let opts = ParseOptions::new().allow_env_mutation(true);
+        let p = EnvPath::from(raw).de_with_options(&opts);
+        assert_eq!(p.to_str(), Some("/opt/app/a"));
+        assert_eq!(env::var_os(name), Some("/opt/app".into()));
+
+        // The variable is set, so the new default is not used.
+        let p = EnvPath::from(["$env: envpath-test-assign := /srv"]).de_with_options(&opts);
+        assert_eq!(p.to_str(), Some("/opt/app"));
+
+        // A `?` chain is not assigned.
+        let p = EnvPath::from(["$env: envpath_test_unset ? envpath_test_assign2 := /x"])
+            .de_with_options(&opts);
+        assert_eq!(p.to_str(), Some("/x"));
+        assert_eq!(env::var_os("ENVPATH_TEST_ASSIGN2"), None);
+
+        env::remove_var(name);
+    }
+
+    #[test]
+    fn split_chain_alternatives() {
+        use super::split_alternatives;
+
+        assert_eq!(
+            split_alternatives("$env: a ?? b ? $dir: cfg"),
+            ["$env: a", "$env: b", "$dir: cfg"]
+        );
+        assert_eq!(split_alternatives("$env: home"), ["$env: home"]);
+        assert_eq!(split_alternatives("a?b"), ["a?b"]);
+        assert_eq!(
+            split_alternatives("$proj(a.b): cfg ? (x.y): data ?? cache"),
+            ["$proj(a.b): cfg", "$proj(x.y): data", "$proj(x.y): cache"]
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use crate::{
     os_cow::{self, into_os_cow},
-    parser::{FULL_COLON, HALF_COLON},
-    EnvPath, OsCow, ProjectDirs,
+    parser::{ParseCtx, COLONS},
+    EnvPath, OsCow, ParseOptions, Platform, ProjectDirs,
 };
 
 #[cfg(windows)]
@@ -18,31 +18,52 @@ use std::{borrow::Cow, io, ops::ControlFlow, path::Path};
 /// This may be a platform-specific parameter, so to avoid the "unused variable" warning, I've added the "_" prefix.
 impl EnvPath<'_> {
     // Method to extract project name information from a string
-    pub(crate) fn get_project_name(c0: &str) -> Option<(&str, &str, Cow<str>)> {
+    //
+    // A part of the name can be a reference (e.g. `$env: ORG` in `$proj(com. $env: ORG. app)`), which is resolved after splitting on `.`, so a resolved value containing `.` is still one part.
+    pub(crate) fn get_project_name(
+        c0: &str,
+    ) -> Option<(Cow<'_, str>, Cow<'_, str>, Cow<'_, str>)> {
         // Find the first and last occurrence of parentheses in the string
         let (start, end) = (c0.find('(')?, c0.rfind(')')?);
 
         // Extract the content within the parentheses
         let content = &c0[start + 1..end];
 
-        // Split the content by periods and trim each part
+        // Split the content by periods, trim each part, and resolve the references
         let parts = content
             .split('.')
             .map(|x| x.trim())
-            .collect::<Vec<_>>();
+            .map(Self::resolve_proj_name_part)
+            .collect::<Option<Vec<_>>>()?;
 
         // Extract the qualifier, organization, and application from the parts
+        let p = |i: usize| parts[i].clone();
         let (qualifier, organization, application) = match parts.len() {
             0 => return None, // If there are no parts, return None
-            1 => (parts[0], "", Cow::from(parts[0])), // If there is only one part, use it as the application name
-            2 => (parts[0], "", Cow::from(parts[1])), // If there are two parts, use the first as the qualifier and the second as the application name
-            3 => (parts[0], parts[1], Cow::from(parts[2])), // If there are three parts, use the first as the qualifier, the second as the organization, and the third as the application name
-            _ => (parts[0], parts[1], Cow::from(parts[2..].concat())), // If there are more than three parts, use the first as the qualifier, the second as the organization, and the rest as the application name
+            1 => (p(0), Cow::from(""), p(0)), // If there is only one part, use it as the application name
+            2 => (p(0), Cow::from(""), p(1)), // If there are two parts, use the first as the qualifier and the second as the application name
+            3 => (p(0), p(1), p(2)), // If there are three parts, use the first as the qualifier, the second as the organization, and the third as the application name
+            _ => (p(0), p(1), Cow::from(parts[2..].concat())), // If there are more than three parts, use the first as the qualifier, the second as the organization, and the rest as the application name
         };
 
         Some((qualifier, organization, application))
     }
 
+    /// Resolves a part of the project name if it is a reference (e.g. `$env: ORG`), otherwise returns it as is.
+    ///
+    /// Returns None if the reference cannot be resolved.
+    fn resolve_proj_name_part(part: &str) -> Option<Cow<'_, str>> {
+        match crate::parser::is_special_chunk(part) {
+            true => crate::parser::resolve_chunk(
+                part,
+                &mut String::new(),
+                ParseCtx::default(),
+            )
+            .map(|x| Cow::from(x.to_string_lossy().into_owned())),
+            _ => Some(Cow::from(part)),
+        }
+    }
+
     // Method to set the project path
     pub(crate) fn set_proj_path<'a>(
         name: &str,
@@ -121,6 +142,7 @@ impl EnvPath<'_> {
         first: &str,
         remain: &'a str,
         separator: char,
+        ctx: ParseCtx,
     ) -> ControlFlow<OsCow<'a>, OsCow<'a>> {
         use ControlFlow::{Break, Continue};
 
@@ -152,7 +174,7 @@ impl EnvPath<'_> {
                     };
 
                     let ident = match x
-                        .rsplit([FULL_COLON, HALF_COLON])
+                        .rsplit(COLONS)
                         .map(|x| x.trim())
                         .next()
                     {
@@ -162,29 +184,63 @@ impl EnvPath<'_> {
                     // dbg!(&name, &proj, &ident);
                     // dbg!(&ident);
 
-                    Continue(Self::match_proj_dirs(ident, &name, proj.as_ref()))
+                    Continue(Self::match_proj_dirs_with(
+                        ident,
+                        c0,
+                        &name,
+                        proj.as_ref(),
+                        ctx,
+                    ))
                 }
                 (p, false) => Break(p),
                 (Some(p), true) => match Path::new(&p) {
-                    x if x.exists() => Break(Some(p)),
+                    x if ctx.is_dry_run() || x.exists() => Break(Some(p)),
                     _ => Continue(None),
                 },
             })
     }
 
+    /// A bare `$proj` (i.e. without the parenthesized identifier) is replaced with `$proj([id])`, if [ParseOptions::default_project()] is set.
+    fn apply_default_project<'c>(first_chunk: &'c str, ctx: ParseCtx) -> Cow<'c, str> {
+        match ctx
+            .options
+            .and_then(|o| o.default_project.as_deref())
+        {
+            Some(id) if !first_chunk.contains('(') => Cow::Owned(format!("$proj({id})")),
+            _ => Cow::Borrowed(first_chunk),
+        }
+    }
+
+    /// Whether `ident` is `runtime` or `state`, which `ProjectDirs` may not provide even if it exists (e.g. if `XDG_RUNTIME_DIR` is unset, or on macOS).
+    ///
+    /// It is used by the strict resolution to report [ParseError::ProjectDirUnavailable](crate::ParseError::ProjectDirUnavailable), rather than an unknown ident.
+    pub(crate) fn is_proj_dir_unavailable(chunk: &str, ident: &str, ctx: ParseCtx) -> bool {
+        matches!(ident, "runtime" | "state")
+            && Self::set_proj_name_opt_tuple(&Self::apply_default_project(chunk, ctx))
+                .is_some_and(|(_, proj)| proj.is_some())
+    }
+
     pub(crate) fn handle_project_dirs<'a>(
         first_chunk: &'a str,
         remain: &'a str,
+        ctx: ParseCtx,
     ) -> OsCow<'a> {
         use ControlFlow::{Break, Continue};
 
+        let first_chunk = &*Self::apply_default_project(first_chunk, ctx);
+
         match Self::get_question_mark_separator(remain) {
-            sep if sep == ' ' => {
+            ' ' => {
                 let (name, proj) = Self::set_proj_name_opt_tuple(first_chunk)?;
-
-                Self::match_proj_dirs(remain, &name, proj.as_ref())
+                Self::match_proj_dirs_with(
+                    remain,
+                    first_chunk,
+                    &name,
+                    proj.as_ref(),
+                    ctx,
+                )
             }
-            sep => match Self::parse_proj_dir_rules(first_chunk, remain, sep) {
+            sep => match Self::parse_proj_dir_rules(first_chunk, remain, sep, ctx) {
                 Break(x) | Continue(x) => x,
             },
         }
@@ -198,11 +254,11 @@ impl EnvPath<'_> {
         let (qual, org, app) = Self::get_project_name(chunk)?;
 
         // Create a ProjectDirs object using the project name information
-        let proj = ProjectDirs::from(qual, org, &app);
+        let proj = ProjectDirs::from(&qual, &org, &app);
 
         // Construct the project name by joining the qualifier, organization, and application
         Some((
-            [qual, org, &app]
+            [&*qual, &*org, &*app]
                 .into_iter()
                 .filter(|x| !x.is_empty())
                 .collect::<Vec<_>>()
@@ -211,6 +267,62 @@ impl EnvPath<'_> {
         ))
     }
 
+    /// Takes the options of `ctx` into account, and then matches the project directory.
+    ///
+    /// The precedence is: [ParseOptions::project_root()], [ParseOptions::simulate_platform()], `ProjectDirs`.
+    ///
+    /// A remix expression (e.g. `env * home`) is resolved with the same `ctx`.
+    fn match_proj_dirs_with<'a>(
+        ident: &'a str,
+        chunk: &str,
+        name: &str,
+        proj: Option<&ProjectDirs>,
+        ctx: ParseCtx,
+    ) -> OsCow<'a> {
+        if Self::starts_with_remix_expr(ident) {
+            return Self::parse_remix_expr(ident, ctx);
+        }
+
+        let options = ctx.options;
+
+        match (
+            options.and_then(|o| o.project_root.as_deref()),
+            options.and_then(ParseOptions::simulated),
+        ) {
+            (Some(root), _) => Self::match_rooted_proj_dirs(ident, name, root),
+            (_, Some(sim)) => Self::match_simulated_proj_dirs(ident, chunk, name, sim),
+            _ => Self::match_proj_dirs(ident, name, proj),
+        }
+    }
+
+    /// Similar to `match_proj_dirs()`, but all the directories are rooted at `root/[name]`, rather than derived from `ProjectDirs`.
+    ///
+    /// | ident                            | path                    |
+    /// | -------------------------------- | ----------------------- |
+    /// | path                             | `$root/com.x.y`         |
+    /// | cache, cli-cache                 | `$root/com.x.y/cache`   |
+    /// | cfg, local-cfg, pref, cli-cfg    | `$root/com.x.y/config`  |
+    /// | data, local-data, cli-data       | `$root/com.x.y/data`    |
+    /// | state                            | `$root/com.x.y/state`   |
+    /// | runtime                          | `$root/com.x.y/runtime` |
+    fn match_rooted_proj_dirs<'a>(ident: &'a str, name: &str, root: &Path) -> OsCow<'a> {
+        let dir = root.join(name);
+        let join = |sub: &str| into_os_cow(dir.join(sub));
+
+        match ident {
+            "path" => into_os_cow(&dir),
+            "cache" | "cli-cache" | "cli_cache" => join("cache"),
+            "cfg" | "config" | "local-cfg" | "local_cfg" | "local_config" | "pref"
+            | "preference" | "cli-cfg" | "cli_cfg" | "cli_config" => join("config"),
+            "data" | "local-data" | "local_data" | "cli-data" | "cli_data" => {
+                join("data")
+            }
+            "state" => join("state"),
+            "runtime" => join("runtime"),
+            x => Self::match_proj_dirs(x, name, None),
+        }
+    }
+
     // Method to handle a project directory request
     pub(crate) fn match_proj_dirs<'a>(
         ident: &'a str,
@@ -278,11 +390,123 @@ impl EnvPath<'_> {
                 opt.and_then(into_os_cow)
             }
             "empty" => os_cow::from_str(""),
-            x if Self::starts_with_remix_expr(x) => Self::parse_remix_expr(x),
             _ => None,
             // If an unknown directory is requested, return None
         }
     }
+    /// Similar to `match_proj_dirs()`, but uses the layout of the simulated platform instead of `ProjectDirs`.
+    ///
+    /// | ident      | Android                                   | Linux                      | MacOs                                 | Windows                                |
+    /// | ---------- | ----------------------------------------- | -------------------------- | ------------------------------------- | -------------------------------------- |
+    /// | path       | `com.x.y`                                 | `y`                        | `com.x.y`                             | `x\y`                                  |
+    /// | cache      | `/data/data/com.x.y/cache`                | `$home/.cache/y`           | `$home/Library/Caches/com.x.y`        | `$home\AppData\Local\x\y\cache`       |
+    /// | cfg        | `/data/data/com.x.y/files`                | `$home/.config/y`          | `$home/Library/Application Support/com.x.y` | `$home\AppData\Roaming\x\y\config` |
+    /// | data       | `/data/data/com.x.y`                      | `$home/.local/share/y`     | `$home/Library/Application Support/com.x.y` | `$home\AppData\Roaming\x\y\data`   |
+    /// | local-data | `/storage/self/primary/Android/data/com.x.y` | `$home/.local/share/y`  | `$home/Library/Application Support/com.x.y` | `$home\AppData\Local\x\y\data`     |
+    /// | local-cfg  | `/storage/self/primary/Android/data/com.x.y/files` | `$home/.config/y` | `$home/Library/Application Support/com.x.y` | `$home\AppData\Local\x\y\config`   |
+    /// | pref       | `/data/data/com.x.y/files`                | `$home/.config/y`          | `$home/Library/Preferences/com.x.y`   | `$home\AppData\Roaming\x\y\config`    |
+    /// | state      | None                                      | `$home/.local/state/y`     | None                                  | None                                   |
+    /// | runtime    | None                                      | None                       | None                                  | None                                   |
+    pub(crate) fn match_simulated_proj_dirs<'a>(
+        ident: &'a str,
+        chunk: &str,
+        name: &str,
+        (platform, home): (Platform, String),
+    ) -> OsCow<'a> {
+        use Platform::*;
+
+        let (qual, org, app) = Self::get_project_name(chunk)?;
+        let (qual, org) = (qual.as_ref(), org.as_ref());
+        let sep = platform.separator().to_string();
+        let join = |parts: &[&str]| into_os_cow(parts.join(&sep));
+
+        // The same naming rules as `directories::ProjectDirs`.
+        let linux_path = app
+            .split_whitespace()
+            .collect::<String>()
+            .to_lowercase();
+        let mac_path = [qual, &org.replace(' ', "-"), &app.replace(' ', "-")]
+            .into_iter()
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join(".");
+
+        let h = home.as_str();
+        let (l, m) = (linux_path.as_str(), mac_path.as_str());
+        let (local, roaming) = (
+            [h, "AppData", "Local", org, &app],
+            [h, "AppData", "Roaming", org, &app],
+        );
+
+        match (platform, ident) {
+            (Android, "path") => into_os_cow(name),
+            (Android, "cache") => join(&["", "data", "data", name, "cache"]),
+            (Android, "cfg" | "config" | "pref" | "preference") => {
+                join(&["", "data", "data", name, "files"])
+            }
+            (Android, "data") => join(&["", "data", "data", name]),
+            (Android, "local-data" | "local_data" | "cli-data" | "cli_data") => {
+                join(&[os_cow::AND_SD, "Android", "data", name])
+            }
+            (
+                Android,
+                "local-cfg" | "local_cfg" | "local_config" | "cli-cfg" | "cli_cfg"
+                | "cli_config",
+            ) => join(&[os_cow::AND_SD, "Android", "data", name, "files"]),
+            (Android, "cli-cache" | "cli_cache") => {
+                join(&["", "data", "data", name, "cache"])
+            }
+
+            (Linux, "path") => into_os_cow(l),
+            (Linux, "cache" | "cli-cache" | "cli_cache") => join(&[h, ".cache", l]),
+            (
+                Linux,
+                "cfg" | "config" | "pref" | "preference" | "local-cfg" | "local_cfg"
+                | "local_config" | "cli-cfg" | "cli_cfg" | "cli_config",
+            ) => join(&[h, ".config", l]),
+            (
+                Linux,
+                "data" | "local-data" | "local_data" | "cli-data" | "cli_data",
+            ) => join(&[h, ".local", "share", l]),
+            (Linux, "state") => join(&[h, ".local", "state", l]),
+
+            (MacOs, "path") => into_os_cow(m),
+            (MacOs, "cache" | "cli-cache" | "cli_cache") => {
+                join(&[h, "Library", "Caches", m])
+            }
+            (MacOs, "pref" | "preference") => join(&[h, "Library", "Preferences", m]),
+            (
+                MacOs,
+                "cfg" | "config" | "local-cfg" | "local_cfg" | "local_config"
+                | "cli-cfg" | "cli_cfg" | "cli_config" | "data" | "local-data"
+                | "local_data" | "cli-data" | "cli_data",
+            ) => join(&[h, "Library", "Application Support", m]),
+
+            (Windows, "path") => join(&[org, &app]),
+            (Windows, "cache" | "cli-cache" | "cli_cache") => {
+                join(&[&local[..], &["cache"]].concat())
+            }
+            (Windows, "cfg" | "config" | "pref" | "preference") => {
+                join(&[&roaming[..], &["config"]].concat())
+            }
+            (Windows, "data") => join(&[&roaming[..], &["data"]].concat()),
+            (Windows, "local-data" | "local_data" | "cli-data" | "cli_data") => {
+                join(&[&local[..], &["data"]].concat())
+            }
+            (
+                Windows,
+                "local-cfg" | "local_cfg" | "local_config" | "cli-cfg" | "cli_cfg"
+                | "cli_config",
+            ) => join(&[&local[..], &["config"]].concat()),
+            (Windows, "local-low" | "local_low") => {
+                join(&[h, "AppData", "LocalLow", name])
+            }
+
+            (_, "runtime" | "state") => None,
+            // e.g. `empty`, `env * HOME`
+            _ => Self::match_proj_dirs(ident, name, None),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -328,5 +552,138 @@ mod tests {
 
         let p2 = EnvPath::new(["$proj * (org. a . b ): runtimes ? env * HOME"]);
         dbg!(p2);
+
+        assert_eq!(
+            EnvPath::new(["$env: envpath_test_none ? proj * (com.xy.z): cfg"]).path,
+            EnvPath::new(["$proj(com.xy.z): cfg"]).path
+        );
+    }
+
+    #[test]
+    fn simulate_android_proj_dirs() {
+        use crate::{ParseOptions, Platform};
+
+        let opts = ParseOptions::new().simulate_platform(Platform::Android);
+        let de = |raw: &str| {
+            EnvPath::from([raw])
+                .de_with_options(&opts)
+                .to_str()
+                .map(str::to_owned)
+        };
+
+        assert_eq!(de("$proj(com.x.y): data").as_deref(), Some("/data/data/com.x.y"));
+        assert_eq!(
+            de("$proj(com.x.y): cfg").as_deref(),
+            Some("/data/data/com.x.y/files")
+        );
+        assert_eq!(
+            de("$proj(com.x.y): local-data").as_deref(),
+            Some("/storage/self/primary/Android/data/com.x.y")
+        );
+        assert_eq!(
+            de("$proj(com.x.y): runtime ? cache").as_deref(),
+            Some("/data/data/com.x.y/cache")
+        );
+    }
+
+    #[test]
+    fn simulate_windows_and_macos_proj_dirs() {
+        use crate::{ParseOptions, Platform};
+
+        let win = ParseOptions::new()
+            .simulate_platform(Platform::Windows)
+            .fake_home(r#"C:\Users\m"#);
+        let p = EnvPath::from(["$proj(com. x. y): local-data"]).de_with_options(&win);
+        assert_eq!(p.to_str(), Some(r#"C:\Users\m\AppData\Local\x\y\data"#));
+
+        let mac = ParseOptions::new().simulate_platform(Platform::MacOs);
+        let p = EnvPath::from(["$proj(com.x.y): state ? cfg"]).de_with_options(&mac);
+        assert_eq!(
+            p.to_str(),
+            Some("/Users/envpath/Library/Application Support/com.x.y")
+        );
+    }
+
+    #[test]
+    fn bare_proj_with_default_project() {
+        use crate::{ParseOptions, Platform};
+
+        let opts = ParseOptions::new()
+            .simulate_platform(Platform::Android)
+            .default_project("com.x.y");
+        let de = |raw: &str| EnvPath::from([raw]).de_with_options(&opts).path;
+
+        assert_eq!(de("$proj: data"), Some("/data/data/com.x.y".into()));
+        // An explicit identifier takes precedence.
+        assert_eq!(de("$proj(org.a.b): data"), Some("/data/data/org.a.b".into()));
+        // A nested identifier also takes precedence over the default.
+        assert_eq!(
+            de("$proj: envpath-none ? (org.a.b): path"),
+            Some("org.a.b".into())
+        );
+
+        let p = EnvPath::from(["$proj: cfg", "a"]).with_default_project("com.x.y");
+        assert_eq!(p.path, EnvPath::new(["$proj(com.x.y): cfg", "a"]).path);
+
+        // Without the default, a bare `$proj` cannot be resolved.
+        assert_eq!(EnvPath::new(["$proj: data"]).to_str(), Some("$proj: data"));
+    }
+
+    #[test]
+    fn proj_name_with_env_reference() {
+        use crate::{ParseOptions, Platform};
+        use std::env;
+
+        env::set_var("ENVPATH_TEST_PROJ_ORG", "acme");
+        env::set_var("ENVPATH_TEST_PROJ_DOTTED", "a.b");
+
+        let android = ParseOptions::new().simulate_platform(Platform::Android);
+        let p = EnvPath::from(["$proj(com. $env: envpath-test-proj-org. app): data"])
+            .de_with_options(&android);
+        assert_eq!(p.to_str(), Some("/data/data/com.acme.app"));
+
+        // The resolved value is still one part, even if it contains `.`.
+        let win = ParseOptions::new()
+            .simulate_platform(Platform::Windows)
+            .fake_home(r#"C:\Users\m"#);
+        let p = EnvPath::from(["$proj(com.$env: envpath_test_proj_dotted.app): path"])
+            .de_with_options(&win);
+        assert_eq!(p.to_str(), Some(r#"a.b\app"#));
+
+        // An unresolved reference makes the whole chunk unresolved.
+        let raw = "$proj(com.$env: envpath_test_proj_missing.app): data";
+        let p = EnvPath::from([raw]).de_with_options(&android);
+        assert_eq!(p.to_str(), Some(raw));
+    }
+
+    #[test]
+    #[cfg(not(target_os = "android"))]
+    fn proj_runtime_dir_unavailable() {
+        use crate::{ParseError, ParseOptions, Platform};
+
+        let raw = "$proj(com.x.y): runtime";
+
+        // The simulated Android has no runtime dir, so the process environment (e.g. `XDG_RUNTIME_DIR`) is left alone.
+        let opts = ParseOptions::new().simulate_platform(Platform::Android);
+        let strict = EnvPath::try_resolve_one(raw, &opts);
+        let unknown = EnvPath::try_resolve_one("$proj(com.x.y): none_such", &opts);
+        let lenient = EnvPath::from([raw]).de_with_options(&opts);
+        let fallback = EnvPath::from(["$proj(com.x.y): runtime ? cache"]).de_with_options(&opts);
+
+        assert_eq!(
+            strict,
+            Err(ParseError::ProjectDirUnavailable {
+                ident: "runtime".into()
+            })
+        );
+        // An unknown ident is still reported as such.
+        assert_eq!(unknown, Err(ParseError::UnknownIdent("none_such".into())));
+
+        // The lenient resolution falls back to the raw str, or to the next alternative of `?`.
+        assert_eq!(lenient.to_str(), Some(raw));
+        assert_eq!(
+            fallback.path,
+            EnvPath::from(["$proj(com.x.y): cache"]).de_with_options(&opts).path
+        );
     }
 }
@@ -1,9 +1,86 @@
-use rand::{distributions::Alphanumeric, Rng};
+use rand::{distributions::Alphanumeric, rngs::StdRng, Rng, RngCore, SeedableRng};
+use std::{
+    env,
+    sync::{Mutex, PoisonError},
+};
+#[cfg(any(test, feature = "test-util"))]
+use std::cell::RefCell;
+
+/// If this environment variable is set to a `u64` (e.g. `ENVPATH_RAND_SEED=42`), the random values are seeded from it, rather than from `thread_rng()`.
+///
+/// This makes the random segments (e.g. `$val: rand-16`) reproducible, e.g. in CI, without code changes at call sites. All threads draw from one process-wide sequence, so two threads (e.g. two `$dir: tmp-rand`) never get the same value.
+///
+/// A value that is not a `u64` is ignored.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::random::{get_random_value, get_random_value_seeded, RAND_SEED_ENV};
+/// use std::{env, thread};
+///
+/// env::set_var(RAND_SEED_ENV, "42");
+/// let a = get_random_value(Some(8));
+/// assert_eq!(a, get_random_value_seeded(Some(8), 42));
+///
+/// let b = thread::spawn(|| get_random_value(Some(8)))
+///     .join()
+///     .unwrap();
+/// assert_ne!(a, b);
+///
+/// // A new seed restarts the sequence.
+/// env::set_var(RAND_SEED_ENV, "7");
+/// assert_eq!(get_random_value(Some(8)), get_random_value_seeded(Some(8), 7));
+///
+/// env::remove_var(RAND_SEED_ENV);
+/// ```
+pub const RAND_SEED_ENV: &str = "ENVPATH_RAND_SEED";
+
+#[cfg(any(test, feature = "test-util"))]
+thread_local! {
+    /// The seeded generator set by `seed_for_testing()`. If it is None, `thread_rng()` is used.
+    static SEEDED_RNG: RefCell<Option<StdRng>> = const { RefCell::new(None) };
+}
+
+/// The process-wide generator seeded from `ENVPATH_RAND_SEED`, along with the seed. It is re-seeded if the variable changes.
+static ENV_RNG: Mutex<Option<(u64, StdRng)>> = Mutex::new(None);
+
+/// Parses the value of [RAND_SEED_ENV].
+fn get_env_seed() -> Option<u64> {
+    env::var(RAND_SEED_ENV)
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Similar to [get_random_value()], but samples from the given random number generator, which makes it deterministic with a seeded `rng`.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::random::get_random_value_with;
+/// use rand::{rngs::StdRng, SeedableRng};
+///
+/// let a = get_random_value_with(&mut StdRng::seed_from_u64(3), Some(10));
+/// let b = get_random_value_with(&mut StdRng::seed_from_u64(3), Some(10));
+/// assert_eq!(a, b);
+/// ```
+pub fn get_random_value_with<R: Rng + ?Sized>(
+    rng: &mut R,
+    rand_length: Option<usize>,
+) -> String {
+    rng.sample_iter(&Alphanumeric) // Sample characters from the alphanumeric distribution.
+        .take(rand_length.unwrap_or(16))
+        .map(char::from) // Map the characters into a String.
+        .collect() // Collect the mapped characters into a single String.
+}
 
 /// Generates a random string of alphanumeric characters using the `rand` crate.
 ///
 /// It takes an optional parameter `rand_length` to specify the length of the random string, defaulting to 16 characters if not provided. The function first imports necessary modules from the `rand` crate and then uses the current thread to generate a random number generator. It then samples characters from the alphanumeric distribution, maps them into a String, and collects them into a single String to return as output.
 ///
+/// If the environment variable [RAND_SEED_ENV] (`ENVPATH_RAND_SEED`) is set, the generator seeded from it is used instead, so the sequence is reproducible. The same applies if `seed_for_testing()` (of the `test-util` feature) has been called on the current thread. See also [get_random_value_with()].
+///
 /// # Examples
 ///
 /// ```
@@ -11,18 +88,223 @@ use rand::{distributions::Alphanumeric, Rng};
 /// dbg!(&val);
 /// ```
 pub fn get_random_value(rand_length: Option<usize>) -> String {
-    rand::thread_rng() // Generate a random number generator using the current thread.
-        .sample_iter(&Alphanumeric) // Sample characters from the alphanumeric distribution.
-        .take(rand_length.unwrap_or(16)) // Take either the provided length or default to 16 characters.
-        .map(char::from) // Map the characters into a String.
-        .collect() // Collect the mapped characters into a single String.
+    with_rng(|rng| get_random_value_with(rng, rand_length))
+}
+
+/// Calls `f` with the seeded generator if `seed_for_testing()` has been called, or with the generator seeded from [RAND_SEED_ENV] if it is set, otherwise with `thread_rng()`.
+fn with_rng<T, F: FnOnce(&mut dyn RngCore) -> T>(f: F) -> T {
+    #[cfg(any(test, feature = "test-util"))]
+    if let Some(mut rng) = SEEDED_RNG.with(|cell| cell.borrow_mut().take()) {
+        let value = f(&mut rng);
+        SEEDED_RNG.with(|cell| *cell.borrow_mut() = Some(rng));
+        return value;
+    }
+
+    with_seed_rng(get_env_seed(), f)
+}
+
+/// Calls `f` with the process-wide generator of `seed` (i.e. the value of [RAND_SEED_ENV]), or with `thread_rng()` if it is None.
+fn with_seed_rng<T, F: FnOnce(&mut dyn RngCore) -> T>(seed: Option<u64>, f: F) -> T {
+    match seed {
+        Some(seed) => {
+            let mut env_rng = ENV_RNG
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            // The variable has changed.
+            if !matches!(*env_rng, Some((s, _)) if s == seed) {
+                *env_rng = None
+            }
+            let (_, rng) =
+                env_rng.get_or_insert_with(|| (seed, StdRng::seed_from_u64(seed)));
+            f(rng)
+        }
+        None => f(&mut rand::thread_rng()), // Generate a random number generator using the current thread.
+    }
+}
+
+/// The alphabet of `$val: rand-base32-[usize]` (RFC 4648).
+pub const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The alphabet of `$val: rand-base64url-[usize]` (RFC 4648, URL and filename safe).
+pub const BASE64URL_ALPHABET: &[u8; 64] = crate::encoding::BASE64URL_ALPHABET;
+
+/// Generates random bytes, and encodes them with `alphabet` (each char holds `bits` bits). The output has exactly `len` chars.
+fn get_random_encoded(len: usize, alphabet: &[u8], bits: usize) -> String {
+    let mut bytes = vec![0; (len * bits).div_ceil(8)];
+    with_rng(|rng| rng.fill_bytes(&mut bytes));
+
+    let mut out = crate::encoding::encode(&bytes, alphabet, bits);
+    out.truncate(len);
+    out
+}
+
+/// Generates a random base32 string (`A-Z`, `2-7`), defaulting to 16 characters if `rand_length` is not provided.
+///
+/// # Examples
+///
+/// ```
+/// let val = envpath::random::get_random_base32(Some(16));
+/// assert_eq!(val.len(), 16);
+/// ```
+pub fn get_random_base32(rand_length: Option<usize>) -> String {
+    get_random_encoded(rand_length.unwrap_or(16), BASE32_ALPHABET, 5)
+}
+
+/// Generates a random base64url string (`A-Z`, `a-z`, `0-9`, `-`, `_`), defaulting to 16 characters if `rand_length` is not provided.
+///
+/// Unlike the standard base64, it is safe for URLs and file names.
+///
+/// # Examples
+///
+/// ```
+/// let val = envpath::random::get_random_base64url(Some(24));
+/// assert_eq!(val.len(), 24);
+/// ```
+pub fn get_random_base64url(rand_length: Option<usize>) -> String {
+    get_random_encoded(rand_length.unwrap_or(16), BASE64URL_ALPHABET, 6)
+}
+
+/// Generates a random string of alphanumeric characters from a fixed `seed`.
+///
+/// The same `seed` and `rand_length` always produce the same string.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::random::get_random_value_seeded;
+///
+/// let a = get_random_value_seeded(Some(8), 42);
+/// let b = get_random_value_seeded(Some(8), 42);
+/// assert_eq!(a, b);
+/// ```
+pub fn get_random_value_seeded(rand_length: Option<usize>, seed: u64) -> String {
+    get_random_value_with(&mut StdRng::seed_from_u64(seed), rand_length)
 }
+
+/// Pins the random sequence of the current thread, which affects `$val: rand-[usize]`, `$dir: tmp-rand` and [get_random_value()].
+///
+/// It is only available in the tests of this crate, or with the `test-util` feature (e.g. in the `[dev-dependencies]` of a downstream crate), so that production code cannot pin the generator. Call [clear_seed_for_testing()] to restore the default behavior.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::random::{clear_seed_for_testing, get_random_value, seed_for_testing};
+///
+/// seed_for_testing(7);
+/// let a = get_random_value(None);
+///
+/// seed_for_testing(7);
+/// let b = get_random_value(None);
+///
+/// assert_eq!(a, b);
+/// clear_seed_for_testing();
+/// ```
+#[cfg(any(test, feature = "test-util"))]
+pub fn seed_for_testing(seed: u64) {
+    SEEDED_RNG.with(|cell| *cell.borrow_mut() = Some(StdRng::seed_from_u64(seed)))
+}
+
+/// Removes the seed set by [seed_for_testing()] on the current thread.
+#[cfg(any(test, feature = "test-util"))]
+pub fn clear_seed_for_testing() {
+    SEEDED_RNG.with(|cell| *cell.borrow_mut() = None)
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
 
     #[test]
     fn random_value() {
         let val = crate::random::get_random_value(Some(3));
         dbg!(val);
     }
+
+    #[test]
+    fn random_base32_and_base64url() {
+        for len in [0, 1, 7, 16, 33] {
+            let v = get_random_base32(Some(len));
+            assert_eq!(v.len(), len);
+            assert!(v.bytes().all(|b| BASE32_ALPHABET.contains(&b)));
+
+            let v = get_random_base64url(Some(len));
+            assert_eq!(v.len(), len);
+            assert!(v.bytes().all(|b| BASE64URL_ALPHABET.contains(&b)));
+        }
+        assert_eq!(get_random_base32(None).len(), 16);
+    }
+
+    #[test]
+    #[cfg(feature = "value")]
+    fn rand_base_val() {
+        use crate::EnvPath;
+
+        let p = EnvPath::new(["$val: rand-base64url-24"]);
+        let s = p.to_str().unwrap();
+        assert_eq!(s.len(), 24);
+        assert!(s.bytes().all(|b| BASE64URL_ALPHABET.contains(&b)));
+
+        let p = EnvPath::new(["$val: rand-base32-10"]);
+        let s = p.to_str().unwrap();
+        assert_eq!(s.len(), 10);
+        assert!(s.bytes().all(|b| BASE32_ALPHABET.contains(&b)));
+    }
+
+    #[test]
+    fn same_seed_same_value() {
+        assert_eq!(
+            get_random_value_seeded(Some(32), 2023),
+            get_random_value_seeded(Some(32), 2023)
+        );
+
+        seed_for_testing(2023);
+        let first = [get_random_value(Some(12)), get_random_value(Some(12))];
+        seed_for_testing(2023);
+        let second = [get_random_value(Some(12)), get_random_value(Some(12))];
+        clear_seed_for_testing();
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn random_value_with_rng() {
+        let mut rng = StdRng::seed_from_u64(99);
+        let a = get_random_value_with(&mut rng, Some(12));
+        let b = get_random_value_with(&mut rng, Some(12));
+        assert_ne!(a, b);
+
+        assert_eq!(a, get_random_value_seeded(Some(12), 99));
+        assert_eq!(get_random_value_with(&mut rng, None).len(), 16);
+    }
+
+    /// The seed is passed directly, rather than via `ENVPATH_RAND_SEED`, since the process environment is shared by the tests that run in parallel. The doctest of [RAND_SEED_ENV] covers the variable.
+    #[test]
+    fn env_seeded_rng() {
+        use std::thread;
+
+        // All threads draw from one sequence.
+        let run = || {
+            thread::spawn(|| with_seed_rng(Some(2029), |rng| get_random_value_with(rng, Some(16))))
+                .join()
+                .unwrap()
+        };
+        let (a, b) = (run(), run());
+
+        assert_ne!(a, b);
+        assert_eq!(a, get_random_value_seeded(Some(16), 2029));
+    }
+
+    #[test]
+    #[cfg(feature = "value")]
+    fn seeded_rand_val() {
+        use crate::EnvPath;
+
+        seed_for_testing(1);
+        let a = EnvPath::new(["$val: rand-20"]);
+        seed_for_testing(1);
+        let b = EnvPath::new(["$val: rand-20"]);
+        clear_seed_for_testing();
+
+        assert_eq!(a, b);
+    }
 }
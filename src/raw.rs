@@ -1,4 +1,7 @@
-use crate::{parser::parse, EnvPath};
+use crate::{
+    parser::{is_special_chunk, parse_with, ParseCtx},
+    EnvPath,
+};
 use std::{borrow::Cow, path::PathBuf};
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq, PartialOrd, Ord)]
@@ -17,12 +20,42 @@ impl<'r> EnvPathRaw<'r> {
             Owned(x) => x.is_empty(),
         }
     }
+    /// Returns an iterator over the raw chunks as `&str`, regardless of the variant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$env: home", "3D Print"]);
+    /// let chunks = path.get_raw().iter().collect::<Vec<_>>();
+    /// assert_eq!(chunks, ["$env: home", "3D Print"]);
+    /// ```
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        use EnvPathRaw::*;
+        let (r, c, o) = match self {
+            Ref(x) => (Some(x), None, None),
+            Cow(x) => (None, Some(x), None),
+            Owned(x) => (None, None, Some(x)),
+        };
+
+        r.into_iter()
+            .flatten()
+            .copied()
+            .chain(c.into_iter().flatten().map(AsRef::as_ref))
+            .chain(o.into_iter().flatten().map(String::as_str))
+    }
+
     pub fn parse(&self) -> Option<PathBuf> {
+        self.parse_with(ParseCtx::default())
+    }
+
+    pub(crate) fn parse_with(&self, ctx: ParseCtx) -> Option<PathBuf> {
         use EnvPathRaw::*;
         match self {
-            Ref(x) => parse(x),
-            Cow(x) => parse(x),
-            Owned(x) => parse(x),
+            Ref(x) => parse_with(x, ctx),
+            Cow(x) => parse_with(x, ctx),
+            Owned(x) => parse_with(x, ctx),
         }
     }
 }
@@ -47,7 +80,7 @@ impl<'r> EnvPath<'r> {
     // pub fn get_raw(&self) -> &[&str] {
     //     self.raw.as_ref()
     // }
-    pub fn get_raw(&self) -> &EnvPathRaw {
+    pub fn get_raw(&self) -> &EnvPathRaw<'_> {
         &self.raw
     }
 
@@ -95,6 +128,204 @@ impl<'r> EnvPath<'r> {
     pub fn clear_raw(&mut self) {
         self.raw = EnvPathRaw::Ref(Vec::new());
     }
+
+    /// Appends a raw chunk, and invalidates the resolved path, so that the next `de()` recomputes it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let mut path = EnvPath::new(["$env: home"]);
+    /// path.push_raw("dev");
+    /// assert!(path.path.is_none());
+    ///
+    /// let path = path.de();
+    /// dbg!(path.display());
+    /// ```
+    pub fn push_raw(&mut self, segment: &'r str) {
+        use std::borrow::Cow::Borrowed;
+        use EnvPathRaw::*;
+
+        match &mut self.raw {
+            Ref(x) => x.push(segment),
+            Cow(x) => x.push(Borrowed(segment)),
+            Owned(x) => x.push(segment.to_owned()),
+        }
+        self.path = None;
+    }
+
+    /// Similar to `push_raw()`, but takes an owned chunk, which does not need to outlive `'r`.
+    ///
+    /// If the raw sequence is `Ref`, it is converted to `Cow` so that the owned chunk can be stored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let mut path = EnvPath::from(["$env: home"]);
+    /// path.push_raw_owned(format!("dev-{}", 1));
+    ///
+    /// dbg!(path.de().display());
+    /// ```
+    pub fn push_raw_owned<S: Into<String>>(&mut self, segment: S) {
+        use std::borrow::Cow::{Borrowed, Owned as OwnedStr};
+        use EnvPathRaw::*;
+
+        let segment = segment.into();
+        match &mut self.raw {
+            Ref(x) => {
+                let mut v = x
+                    .drain(..)
+                    .map(Borrowed)
+                    .collect::<Vec<_>>();
+                v.push(OwnedStr(segment));
+                self.raw = Cow(v);
+            }
+            Cow(x) => x.push(OwnedStr(segment)),
+            Owned(x) => x.push(segment),
+        }
+        self.path = None;
+    }
+
+    /// The chainable version of `push_raw()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::from(["$dir: cfg"])
+    ///     .join_raw("envpath")
+    ///     .join_raw("cfg.toml")
+    ///     .de();
+    ///
+    /// dbg!(path.display());
+    /// ```
+    pub fn join_raw(mut self, segment: &'r str) -> Self {
+        self.push_raw(segment);
+        self
+    }
+
+    /// Cleans up a machine-built raw sequence, and invalidates the resolved path.
+    ///
+    /// - Empty or whitespace-only chunks are removed.
+    /// - Chunks that only consist of separators (e.g. `/`, or `\` on Windows) are removed, unless it is the first chunk (i.e. the root). Joining such a chunk in the middle would reset the path to the root.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let mut path = EnvPath::from(["/", "/", "", "  ", "app", "/", "x"]);
+    /// path.merge_raw_dedup();
+    ///
+    /// let chunks = path.get_raw().iter().collect::<Vec<_>>();
+    /// assert_eq!(chunks, ["/", "app", "x"]);
+    /// ```
+    pub fn merge_raw_dedup(&mut self) {
+        use EnvPathRaw::*;
+
+        fn dedup<S: AsRef<str>>(v: &mut Vec<S>) {
+            let is_sep_only = |s: &str| s.chars().all(std::path::is_separator);
+            let mut is_first = true;
+
+            v.retain(|s| {
+                let s = s.as_ref();
+                let keep = !s.trim().is_empty() && (is_first || !is_sep_only(s));
+                is_first &= !keep;
+                keep
+            });
+        }
+
+        match &mut self.raw {
+            Ref(x) => dedup(x),
+            Cow(x) => dedup(x),
+            Owned(x) => dedup(x),
+        }
+        self.path = None;
+    }
+
+    /// Compares the raw templates of two `EnvPath`s chunk by chunk, ignoring the resolved path and the variant of `raw` (`Ref`, `Cow` or `Owned`).
+    ///
+    /// Unlike the derived `PartialEq`, `EnvPath::from(["a"])` and `EnvPath::new_owned(["a"])` are equal here.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let a = EnvPath::new(["$dir: cfg", "app"]);
+    /// let b = EnvPath::new_owned(["$dir: cfg", "app"]);
+    ///
+    /// assert!(a.raw_eq(&b));
+    /// assert_ne!(a, b);
+    /// ```
+    pub fn raw_eq(&self, other: &EnvPath<'_>) -> bool {
+        self.raw
+            .iter()
+            .eq(other.raw.iter())
+    }
+
+    /// Splits the path into the resolved base directory and the literal tail.
+    ///
+    /// The base is everything up to and including the last special chunk (e.g. `$dir: data`), and the tail is the trailing literal chunks joined together.
+    ///
+    /// If there is no special chunk, the base is empty. If the last chunk is special, the tail is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    /// use std::path::Path;
+    ///
+    /// let path = EnvPath::from(["$dir: data", "sub", "x.toml"]);
+    /// let (base, tail) = path.split_at_special();
+    ///
+    /// dbg!(base.display());
+    /// assert_eq!(tail, Path::new("sub").join("x.toml"));
+    /// ```
+    pub fn split_at_special(&self) -> (PathBuf, PathBuf) {
+        let chunks = self.raw.iter().collect::<Vec<_>>();
+
+        let idx = chunks
+            .iter()
+            .rposition(|s| is_special_chunk(s))
+            .map_or(0, |i| i + 1);
+        let (base, tail) = chunks.split_at(idx);
+
+        let parse = |x: &[&str]| {
+            parse_with(x, ParseCtx::default()).unwrap_or_default()
+        };
+
+        (parse(base), parse(tail))
+    }
+
+    /// Splits the raw chunks at `idx` into two owned `EnvPath`s, the base prefix (`[0, idx)`) and the tail (`[idx, len)`), which can be resolved independently (e.g. with `de()`).
+    ///
+    /// If `idx` is out of range, it is clamped to the length, so the tail is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let template = EnvPath::from(["$dir: cfg", "app", "x.toml"]);
+    /// let (base, tail) = template.split_raw_at(1);
+    ///
+    /// assert_eq!(base.get_raw().iter().collect::<Vec<_>>(), ["$dir: cfg"]);
+    /// assert_eq!(tail.get_raw().iter().collect::<Vec<_>>(), ["app", "x.toml"]);
+    ///
+    /// let base = base.de();
+    /// dbg!(base.display());
+    /// ```
+    pub fn split_raw_at(&self, idx: usize) -> (EnvPath<'static>, EnvPath<'static>) {
+        // `take()` and `skip()` clamp `idx` to the length.
+        let chunks = || self.raw.iter().map(String::from);
+
+        (chunks().take(idx).collect(), chunks().skip(idx).collect())
+    }
 }
 
 #[cfg(test)]
@@ -111,4 +342,129 @@ mod tests {
 
         assert!(!path.exists());
     }
+
+    #[test]
+    #[cfg(feature = "consts")]
+    fn split_at_special_chunk() {
+        use std::path::Path;
+
+        let path = EnvPath::from(["$dir: data", "sub", "x.toml"]);
+        let (base, tail) = path.split_at_special();
+
+        assert_eq!(Some(base), EnvPath::new(["$dir: data"]).path);
+        assert_eq!(tail, Path::new("sub").join("x.toml"));
+
+        let (base, tail) = EnvPath::from(["sub", "$const: empty"]).split_at_special();
+        assert_eq!((base.as_path(), tail.as_path()), (Path::new("sub"), Path::new("")));
+
+        let (base, tail) = EnvPath::from(["sub", "x.toml"]).split_at_special();
+        assert_eq!(base, Path::new(""));
+        assert_eq!(tail, Path::new("sub/x.toml"));
+    }
+
+    #[test]
+    #[cfg(feature = "dirs")]
+    fn split_raw_at_index() {
+        use std::path::Path;
+
+        let template = EnvPath::new(["$dir: cfg", "$env: envpath_test_unset :- app", "x.toml"]);
+        let raw = |p: &EnvPath| p.get_raw().iter().map(String::from).collect::<Vec<_>>();
+
+        let (base, tail) = template.split_raw_at(1);
+        assert_eq!(raw(&base), ["$dir: cfg"]);
+        assert_eq!(raw(&tail), ["$env: envpath_test_unset :- app", "x.toml"]);
+        assert_eq!(base.path, None);
+
+        let (base, tail) = (base.de(), tail.de());
+        assert_eq!(base.path, EnvPath::new(["$dir: cfg"]).path);
+        assert_eq!(tail.path.as_deref(), Some(Path::new("app").join("x.toml").as_path()));
+        assert_eq!(
+            base.path.map(|x| x.join(tail.path.unwrap_or_default())),
+            template.path
+        );
+
+        // Out of range
+        let (base, tail) = template.split_raw_at(9);
+        assert!(base.raw_eq(&template));
+        assert!(tail.get_raw().is_empty());
+
+        let (base, tail) = template.split_raw_at(0);
+        assert!(base.get_raw().is_empty());
+        assert!(tail.raw_eq(&template));
+    }
+
+    #[test]
+    #[cfg(feature = "consts")]
+    fn push_raw_to_every_variant() {
+        use std::borrow::Cow;
+
+        let mut path = EnvPath::new(["$const: empty", "a"]);
+        path.push_raw("b");
+        assert_eq!(path.path, None);
+        assert_eq!(path.de().path, Some(["a", "b"].iter().collect()));
+
+        let path = EnvPath::new_cow([Cow::Borrowed("a")]).join_raw("b").de();
+        assert_eq!(path.path, Some(["a", "b"].iter().collect()));
+
+        let mut path = EnvPath::new_owned(["a"]);
+        {
+            // The owned chunk does not need to outlive `'r`.
+            let short_lived = String::from("b");
+            path.push_raw_owned(short_lived.as_str());
+        }
+        assert!(matches!(path.get_raw(), EnvPathRaw::Owned(_)));
+        assert_eq!(path.de().path, Some(["a", "b"].iter().collect()));
+
+        let mut path = EnvPath::from(["a"]);
+        path.push_raw_owned(String::from("b"));
+        assert!(matches!(path.get_raw(), EnvPathRaw::Cow(_)));
+        assert_eq!(path.de().path, Some(["a", "b"].iter().collect()));
+    }
+
+    #[test]
+    fn merge_raw_dedup_every_variant() {
+        use std::borrow::Cow;
+
+        let expected = ["a", "b"];
+
+        let mut path = EnvPath::new(["", "a", " ", "/", "/", "\t", "b", ""]);
+        path.merge_raw_dedup();
+        assert_eq!(path.path, None);
+        assert_eq!(path.get_raw().iter().collect::<Vec<_>>(), expected);
+        assert_eq!(path.de().path, Some(["a", "b"].iter().collect()));
+
+        let mut path = EnvPath::new_owned(["a", "", "/", "/", "b"]);
+        path.merge_raw_dedup();
+        assert_eq!(path.get_raw().iter().collect::<Vec<_>>(), expected);
+
+        let mut path = EnvPath::new_cow([Cow::Borrowed("a"), "  ".into(), "/".into(), "b".into()]);
+        path.merge_raw_dedup();
+        assert_eq!(path.get_raw().iter().collect::<Vec<_>>(), expected);
+
+        // The leading root is kept.
+        let mut path = EnvPath::from([" ", "/", "/", "a", "/"]);
+        path.merge_raw_dedup();
+        assert_eq!(path.get_raw().iter().collect::<Vec<_>>(), ["/", "a"]);
+    }
+
+    #[test]
+    fn raw_eq_across_variants() {
+        let segments = ["$dir: cfg", "app", "x.toml"];
+
+        let r = EnvPath::from(segments);
+        let o = EnvPath::new_owned(segments);
+        assert!(matches!(r.get_raw(), EnvPathRaw::Ref(_)));
+        assert!(matches!(o.get_raw(), EnvPathRaw::Owned(_)));
+
+        assert!(r.raw_eq(&o));
+        assert!(o.raw_eq(&r));
+        assert_ne!(r, o);
+
+        let mut c = EnvPath::from(["$dir: cfg", "app"]);
+        c.push_raw_owned("x.toml");
+        assert!(c.raw_eq(&r));
+
+        assert!(!r.raw_eq(&EnvPath::from(["$dir: cfg", "app"])));
+        assert!(EnvPath::default().raw_eq(&EnvPath::new_owned(Vec::<String>::new())));
+    }
 }
@@ -1,22 +1,16 @@
 use crate::{raw::EnvPathRaw, EnvPath};
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{
+    de::{IgnoredAny, MapAccess, SeqAccess, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{borrow::Cow, fmt};
 
 impl Serialize for EnvPath<'_> {
     /// Just serialize the `raw`, the `path` is not needed.
     /// Since the value of `$env` needs to be fetched at runtime, `path` is not serialized by default.
     ///
-    /// If you really want to serialize the value of `path`, then you can create a new struct or other data structures.
-    /// Here path_arr refers to `raw` and you need to manually set `path_str` to the value of `path`.
-    ///
-    ///```no_run
-    /// use envpath::EnvPath;
-    /// use std::path::PathBuf;
-    ///
-    /// struct Cfg<'a> {
-    ///   path_arr: EnvPath<'a>,
-    ///   path_str: PathBuf,
-    /// }
-    ///```
+    /// If you really want to serialize the value of `path` (e.g. for snapshotting), use [EnvPath::with_resolved()].
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
@@ -30,17 +24,194 @@ impl Serialize for EnvPath<'_> {
     }
 }
 
+/// A serializable view of an `EnvPath`, which emits both `raw` and the resolved `path`, i.e. `{ raw, path }`.
+///
+/// It is created by [EnvPath::with_resolved()].
+#[derive(Debug, Clone, Copy)]
+pub struct WithResolved<'p, 'r>(&'p EnvPath<'r>);
+
+impl<'r> EnvPath<'r> {
+    /// Wraps it for serialization, so that the resolved `path` is written out along with `raw`.
+    ///
+    /// On deserialization, `path` is ignored and recomputed from `raw`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    ///
+    /// let path = EnvPath::new(["$env: home", "data"]);
+    /// let ron = ron::to_string(&path.with_resolved()).unwrap();
+    /// assert!(ron.starts_with(r#"(raw:["$env: home","data"],path:"#));
+    /// ```
+    pub fn with_resolved(&self) -> WithResolved<'_, 'r> {
+        WithResolved(self)
+    }
+}
+
+impl Serialize for WithResolved<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("EnvPath", 2)?;
+        state.serialize_field("raw", self.0)?;
+        state.serialize_field("path", &self.0.path)?;
+        state.end()
+    }
+}
+
+/// A module for `#[serde(with = "envpath::resolved")]`, which serializes an `EnvPath` field as `{ raw, path }` (see [EnvPath::with_resolved()]).
+///
+/// On deserialization, the stored `path` is ignored and recomputed from `raw`.
+///
+/// # Examples
+///
+/// ```
+/// use envpath::EnvPath;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// struct Cfg<'a> {
+///     #[serde(with = "envpath::resolved")]
+///     dir: EnvPath<'a>,
+/// }
+///
+/// let cfg = Cfg { dir: EnvPath::new(["$env: home"]) };
+/// let ron = ron::to_string(&cfg).unwrap();
+/// assert!(ron.contains("path:"));
+/// ```
+pub mod resolved {
+    use crate::EnvPath;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Serializes both `raw` and the resolved `path`.
+    pub fn serialize<S>(path: &EnvPath, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        path.with_resolved().serialize(serializer)
+    }
+
+    /// Deserializes the `raw` (the stored `path` is ignored), and resolves it.
+    pub fn deserialize<'de, 'r, D>(deserializer: D) -> Result<EnvPath<'r>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        EnvPath::deserialize(deserializer)
+    }
+}
+
+/// Accepts either a sequence of strings (e.g. `["$env: home", "data"]`), or a single string (e.g. `"$env: home"`), which is a raw sequence of one chunk.
+///
+/// A map written by [EnvPath::with_resolved()] (i.e. `{ raw, path }`) is also accepted, but only its `raw` is used.
+struct RawVisitor;
+
+impl<'de> Visitor<'de> for RawVisitor {
+    type Value = Vec<Cow<'static, str>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string or a sequence of strings")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(vec![Cow::Owned(v.to_owned())])
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(vec![Cow::Owned(v)])
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut raw = Vec::with_capacity(seq.size_hint().unwrap_or(4));
+        while let Some(x) = seq.next_element()? {
+            raw.push(x)
+        }
+        Ok(raw)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut raw = None;
+        while let Some(key) = map.next_key::<Field>()? {
+            match key {
+                Field::Raw => raw = Some(map.next_value::<RawSeed>()?.0),
+                // `path` is recomputed from `raw`.
+                Field::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        raw.ok_or_else(|| serde::de::Error::missing_field("raw"))
+    }
+}
+
+/// The key of a map. Since it is deserialized as an identifier, the keys of a RON struct (e.g. `(raw: [], path: None)`) are also accepted.
+enum Field {
+    Raw,
+    Other,
+}
+
+impl<'de> Deserialize<'de> for Field {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FieldVisitor;
+
+        impl Visitor<'_> for FieldVisitor {
+            type Value = Field;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a field name")
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(match v {
+                    "raw" => Field::Raw,
+                    _ => Field::Other,
+                })
+            }
+        }
+
+        deserializer.deserialize_identifier(FieldVisitor)
+    }
+}
+
+/// The `raw` field of a map, which may also be a string or a sequence of strings.
+struct RawSeed(Vec<Cow<'static, str>>);
+
+impl<'de> Deserialize<'de> for RawSeed {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(RawVisitor).map(Self)
+    }
+}
+
 // Implement the Deserialize trait for EnvPath
 impl<'de> Deserialize<'de> for EnvPath<'_> {
-    // Implement the deserialize method to parse a sequence of strings into an EnvPath instance
+    // Implement the deserialize method to parse a string or a sequence of strings into an EnvPath instance
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        // Deserialize the incoming sequence of strings into an EnvPathRaw instance
+        // Deserialize the incoming string (or sequence of strings) into an EnvPathRaw instance.
+        // A non-self-describing format (e.g. bincode) cannot `deserialize_any`, and `Serialize` always writes a sequence, so it is read as a sequence.
+        let raw = match deserializer.is_human_readable() {
+            true => deserializer.deserialize_any(RawVisitor)?,
+            false => deserializer.deserialize_seq(RawVisitor)?,
+        };
+
         // Create a new instance, and deserialize it.
         let new = EnvPath {
-            raw: EnvPathRaw::Cow(Vec::deserialize(deserializer)?),
+            raw: EnvPathRaw::Cow(raw),
             path: None,
         }
         .de();
@@ -73,6 +244,7 @@ mod tests {
 
         // Struct with data_dir field, of Option EnvPath type
         #[derive(Serialize, Debug, Deserialize)]
+        #[allow(dead_code)]
         struct Cfg<'a> {
             data_dir: Option<EnvPath<'a>>,
         }
@@ -92,6 +264,169 @@ mod tests {
         }
     }
 
+    #[test]
+    fn deser_scalar_and_seq() {
+        #[derive(Debug, Deserialize)]
+        struct Cfg<'a> {
+            dir: EnvPath<'a>,
+        }
+
+        let scalar = EnvPath::new(["$env: home"]);
+        let seq = EnvPath::new(["$env: home", "data"]);
+        let same = |a: &EnvPath, b: &EnvPath| a.raw_eq(b) && a.path == b.path;
+
+        let de_ron = |s: &str| ron::from_str::<Cfg>(s).expect("Failed to deser ron").dir;
+        assert!(same(&de_ron(r#"(dir: "$env: home")"#), &scalar));
+        assert!(same(&de_ron(r#"(dir: ["$env: home", "data"])"#), &seq));
+
+        let de_json =
+            |s: &str| serde_json::from_str::<Cfg>(s).expect("Failed to deser json").dir;
+        assert!(same(&de_json(r#"{"dir": "$env: home"}"#), &scalar));
+        assert!(same(&de_json(r#"{"dir": ["$env: home", "data"]}"#), &seq));
+
+        assert!(serde_json::from_str::<Cfg>(r#"{"dir": 1}"#).is_err());
+    }
+
+    #[test]
+    fn round_trip_non_self_describing() {
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Cfg<'a> {
+            dir: EnvPath<'a>,
+            n: u8,
+        }
+
+        let cfg = Cfg {
+            dir: EnvPath::new(["$env: home", "app"]),
+            n: 7,
+        };
+        let bytes = bincode::serialize(&cfg).unwrap();
+        let de = bincode::deserialize::<Cfg>(&bytes).unwrap();
+
+        assert!(de.dir.raw_eq(&cfg.dir));
+        assert_eq!(de.dir.path, cfg.dir.path);
+        assert_eq!(de.n, 7);
+    }
+
+    #[test]
+    fn round_trip_scalar_and_seq() {
+        let p = EnvPath::new(["$env: home", "app"]);
+        let same = |a: &EnvPath, b: &EnvPath| a.raw_eq(b) && a.path == b.path;
+
+        // The serialized form is always a sequence.
+        let ron = ron::to_string(&p).unwrap();
+        assert_eq!(ron, r#"["$env: home","app"]"#);
+        assert!(same(&ron::from_str::<EnvPath>(&ron).unwrap(), &p));
+
+        let json = serde_json::to_string(&p).unwrap();
+        assert_eq!(json, r#"["$env: home","app"]"#);
+        assert!(same(&serde_json::from_str::<EnvPath>(&json).unwrap(), &p));
+
+        // A single string is read as a raw of one chunk, and written back as a sequence.
+        let scalar = ron::from_str::<EnvPath>(r#""$env: home""#).unwrap();
+        assert_eq!(ron::to_string(&scalar).unwrap(), r#"["$env: home"]"#);
+
+        let scalar = serde_json::from_str::<EnvPath>(r#""$env: home""#).unwrap();
+        assert_eq!(serde_json::to_string(&scalar).unwrap(), r#"["$env: home"]"#);
+    }
+
+    #[test]
+    fn round_trip_concrete_path() {
+        use std::path::{Path, PathBuf};
+
+        let mut paths = vec![
+            PathBuf::from("/srv/app"),
+            Path::new("$env: home").join("app"),
+            PathBuf::from("$Recycle.Bin"),
+        ];
+        #[cfg(unix)]
+        paths.push(PathBuf::from(r"/srv/a\:b\\c"));
+
+        for path in paths {
+            let p = EnvPath::from(path.as_path());
+            let json = serde_json::to_string(&p).unwrap();
+            let de = serde_json::from_str::<EnvPath>(&json).unwrap();
+
+            assert_eq!(de.path.as_deref(), Some(path.as_path()), "{json}");
+            assert!(de.raw_eq(&p));
+        }
+    }
+
+    /// Locks the wire format: the raw of every scheme must survive a round trip byte for byte.
+    #[test]
+    fn round_trip_every_scheme() {
+        let raws: &[&[&str]] = &[
+            &["$env: home", "app"],
+            &["$env: xdg_data_home :- /tmp/fallback"],
+            &["$dir: cfg", "$dir: cache"],
+            &["$const: os", "$const: arch-alias: docker"],
+            &["$proj(com.x.y): data"],
+            &["$proj (org . moz . ff )：runtime ？ data ？？ state ？"],
+            &["$val: rand-16", "$val: nproc"],
+            &["$dir: dl ? env * HOME", "$const: empty ?? dir * config"],
+            &["$env: user ? userprofile ?? home", "$dir: cfg ?? data ? tmp"],
+            &["\\$env: home", "a\"b\tc", "\u{1F980}"],
+        ];
+        let raw = |p: &EnvPath| p.get_raw().iter().map(String::from).collect::<Vec<_>>();
+
+        for &chunks in raws {
+            let p = EnvPath::from(chunks);
+
+            let ron = ron::to_string(&p).expect("Failed to ser ron");
+            let de = ron::from_str::<EnvPath>(&ron).expect("Failed to deser ron");
+            assert_eq!(raw(&de), chunks, "ron: {ron}");
+
+            let json = serde_json::to_string(&p).expect("Failed to ser json");
+            let de = serde_json::from_str::<EnvPath>(&json).expect("Failed to deser json");
+            assert_eq!(raw(&de), chunks, "json: {json}");
+        }
+    }
+
+    #[test]
+    fn ser_with_resolved() {
+        let p = EnvPath::new(["$env: home", "data"]);
+        let path = p.path.as_deref().expect("Failed to resolve").to_string_lossy();
+
+        let ron = ron::to_string(&p.with_resolved()).expect("Failed to ser");
+        assert!(ron.contains(&*path));
+        // The plain `EnvPath` only writes the raw.
+        assert!(!ron::to_string(&p).unwrap().contains(&*path));
+
+        // On read-back, the stored path is ignored, and it is recomputed from the raw.
+        let stale = ron.replace(&*path, "/stale/path");
+        let de = ron::from_str::<EnvPath>(&stale).expect("Failed to deser");
+        assert!(de.raw_eq(&p));
+        assert_eq!(de.path, p.path);
+
+        let json = serde_json::to_string(&p.with_resolved()).unwrap();
+        let de = serde_json::from_str::<EnvPath>(&json).expect("Failed to deser json");
+        assert!(de.raw_eq(&p));
+
+        assert!(serde_json::from_str::<EnvPath>(r#"{"path": "/a"}"#).is_err());
+    }
+
+    #[test]
+    fn serde_with_resolved_module() {
+        #[derive(Debug, Serialize, Deserialize)]
+        struct Cfg<'a> {
+            #[serde(with = "crate::resolved")]
+            dir: EnvPath<'a>,
+        }
+
+        let cfg = Cfg {
+            dir: EnvPath::new(["$dir: cfg", "app"]),
+        };
+        let path = cfg.dir.to_string();
+
+        let ron = ron::to_string(&cfg).expect("Failed to ser");
+        assert!(ron.contains(&path));
+
+        let de = ron::from_str::<Cfg>(&ron.replace(&path, "/stale"))
+            .expect("Failed to deser")
+            .dir;
+        assert!(de.raw_eq(&cfg.dir));
+        assert_eq!(de.path, cfg.dir.path);
+    }
+
     #[test]
     fn readme_doc_quick_start_0() {
         let v =
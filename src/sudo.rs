@@ -0,0 +1,152 @@
+use crate::dirs::{DirsBackend, EnvLookup};
+use std::{
+    ffi::{CStr, CString, OsStr},
+    mem,
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::PathBuf,
+    ptr,
+};
+
+/// The home directory of `SUDO_USER` (i.e. the user who invoked `sudo`), via `getpwnam_r`.
+///
+/// `SUDO_USER` is obtained from `env`. Returns None if it is unset, or if the user cannot be found.
+pub(crate) fn sudo_user_home(env: EnvLookup) -> Option<PathBuf> {
+    let name = CString::new(env("SUDO_USER")?.into_vec()).ok()?;
+
+    let mut buf = vec![0; 16384];
+    // Safety: `passwd` is a plain C struct, so all-zero is a valid (empty) value.
+    let mut pwd: libc::passwd = unsafe { mem::zeroed() };
+    let mut result = ptr::null_mut();
+
+    // Safety: On success, the strings of `pwd` point into `buf`, which outlives them.
+    unsafe {
+        let code = libc::getpwnam_r(
+            name.as_ptr(),
+            &mut pwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        );
+        if code != 0 || result.is_null() || pwd.pw_dir.is_null() {
+            return None;
+        }
+        let dir = CStr::from_ptr(pwd.pw_dir).to_bytes();
+        Some(PathBuf::from(OsStr::from_bytes(dir)))
+    }
+}
+
+/// Only `home` is replaced with the home of `SUDO_USER`, the other directories come from the inner backend.
+pub(crate) struct SudoUserDirs<'b> {
+    pub(crate) home: PathBuf,
+    pub(crate) inner: &'b dyn DirsBackend,
+}
+
+impl DirsBackend for SudoUserDirs<'_> {
+    fn home_dir(&self) -> Option<PathBuf> {
+        Some(self.home.clone())
+    }
+    fn cache_dir(&self) -> Option<PathBuf> {
+        self.inner.cache_dir()
+    }
+    fn config_dir(&self) -> Option<PathBuf> {
+        self.inner.config_dir()
+    }
+    fn config_local_dir(&self) -> Option<PathBuf> {
+        self.inner.config_local_dir()
+    }
+    fn data_dir(&self) -> Option<PathBuf> {
+        self.inner.data_dir()
+    }
+    fn data_local_dir(&self) -> Option<PathBuf> {
+        self.inner.data_local_dir()
+    }
+    fn preference_dir(&self) -> Option<PathBuf> {
+        self.inner.preference_dir()
+    }
+    fn runtime_dir(&self) -> Option<PathBuf> {
+        self.inner.runtime_dir()
+    }
+    fn state_dir(&self) -> Option<PathBuf> {
+        self.inner.state_dir()
+    }
+    fn audio_dir(&self) -> Option<PathBuf> {
+        self.inner.audio_dir()
+    }
+    fn desktop_dir(&self) -> Option<PathBuf> {
+        self.inner.desktop_dir()
+    }
+    fn document_dir(&self) -> Option<PathBuf> {
+        self.inner.document_dir()
+    }
+    fn download_dir(&self) -> Option<PathBuf> {
+        self.inner.download_dir()
+    }
+    fn executable_dir(&self) -> Option<PathBuf> {
+        self.inner.executable_dir()
+    }
+    fn font_dir(&self) -> Option<PathBuf> {
+        self.inner.font_dir()
+    }
+    fn picture_dir(&self) -> Option<PathBuf> {
+        self.inner.picture_dir()
+    }
+    fn public_dir(&self) -> Option<PathBuf> {
+        self.inner.public_dir()
+    }
+    fn template_dir(&self) -> Option<PathBuf> {
+        self.inner.template_dir()
+    }
+    fn video_dir(&self) -> Option<PathBuf> {
+        self.inner.video_dir()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parser::ParseCtx, EnvPath, ParseOptions};
+    use std::{collections::HashMap, ffi::OsString};
+
+    #[test]
+    fn sudo_user_home_dir() {
+        let sudo = |user: &str| {
+            ParseOptions::new()
+                .respect_sudo_user(true)
+                .env_map([("SUDO_USER", user)])
+        };
+        let de = |opts| EnvPath::from(["$dir: home"]).de_with_options(&opts).path;
+
+        // `root` exists on every Unix.
+        let root = |var: &str| (var == "SUDO_USER").then(|| OsString::from("root"));
+        let home = sudo_user_home(&root).expect("Failed to get the home of root");
+        assert!(home.is_absolute());
+        assert_eq!(de(sudo("root")), Some(home.clone()));
+
+        // Off by default.
+        let off = ParseOptions::new().env_map([("SUDO_USER", "root")]);
+        assert_eq!(de(off), dirs::home_dir());
+
+        // Under sudo, `HOME` is usually the home of root, so it does not override the home of `SUDO_USER`. The XDG variables are still consulted.
+        let map = HashMap::from([
+            ("SUDO_USER".to_owned(), OsString::from("root")),
+            ("HOME".to_owned(), OsString::from("/envpath-sudo-home")),
+            ("XDG_STATE_HOME".to_owned(), OsString::from("/srv/state")),
+        ]);
+        let both = ParseOptions::new()
+            .respect_sudo_user(true)
+            .env_first(true);
+        let ctx = ParseCtx {
+            env_map: Some(&map),
+            options: Some(&both),
+            ..Default::default()
+        };
+        let dir = |ident| EnvPath::match_base_dirs_ctx(ident, ctx).map(|x| PathBuf::from(&*x));
+        assert_eq!(dir("home"), Some(home));
+        assert_eq!(dir("state"), Some(PathBuf::from("/srv/state")));
+
+        // An unknown user falls back to the home of the current user.
+        let unknown = |_: &str| Some(OsString::from("envpath-no-such-user"));
+        assert_eq!(sudo_user_home(&unknown), None);
+        assert_eq!(de(sudo("envpath-no-such-user")), dirs::home_dir());
+    }
+}
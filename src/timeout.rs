@@ -0,0 +1,89 @@
+use crate::{EnvPath, ParseOptions};
+use std::{path::PathBuf, sync::mpsc, thread, time::Duration};
+
+impl EnvPath<'_> {
+    /// Similar to `de()`, but the resolution runs on a worker thread, and gives up after `timeout`.
+    ///
+    /// On network filesystems, the existence checks of `??` (i.e. `exists()`) may hang. If the resolution does not complete in time, the literal fallback is used, i.e. the raw chunks are joined as is (e.g. `$dir: data ?? cfg/app`).
+    ///
+    /// Note: The worker thread cannot be cancelled, so it keeps running in the background until the slow probe returns.
+    ///
+    /// To combine it with other settings, use [ParseOptions::timeout()] instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use envpath::EnvPath;
+    /// use std::time::Duration;
+    ///
+    /// let path = EnvPath::from(["$dir: data ?? cfg", "app"])
+    ///     .de_with_timeout(Duration::from_secs(2));
+    ///
+    /// dbg!(path.display());
+    /// ```
+    pub fn de_with_timeout(self, timeout: Duration) -> Self {
+        self.de_with_options(&ParseOptions::new().timeout(timeout))
+    }
+}
+
+/// Parses `chunks` with `options` on a worker thread, and gives up after `timeout` (see [EnvPath::de_with_timeout()]).
+pub(crate) fn parse_with_timeout<S: AsRef<str>>(
+    chunks: &[S],
+    options: &ParseOptions,
+    timeout: Duration,
+) -> Option<PathBuf> {
+    // The worker thread may outlive the caller, so it needs its own copy of the raw chunks and options.
+    let owned = chunks
+        .iter()
+        .map(|x| x.as_ref().to_owned())
+        .collect::<Vec<_>>();
+    let options = options.clone();
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        // If the receiver has given up, the result is simply dropped.
+        let _ = tx.send(options.parse(&owned));
+    });
+
+    rx.recv_timeout(timeout)
+        .unwrap_or_else(|_| {
+            Some(
+                chunks
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .collect::<PathBuf>(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{EnvPath, ParseOptions};
+    use std::{ffi::OsString, path::PathBuf, thread, time::Duration};
+
+    #[test]
+    fn timeout_of_slow_probe() {
+        let slow = |name: &str| {
+            thread::sleep(Duration::from_millis(500));
+            Some(OsString::from(format!("/slow/{name}")))
+        };
+        let raw = ["$env: envpath-test-slow-probe", "app"];
+
+        let opts = ParseOptions::new().resolver(slow);
+
+        let path = EnvPath::from(raw)
+            .de_with_options(&opts.clone().timeout(Duration::from_millis(20)));
+        assert_eq!(path.path, Some(raw.iter().collect::<PathBuf>()));
+
+        let path = EnvPath::from(raw)
+            .de_with_options(&opts.timeout(Duration::from_secs(10)));
+        assert_eq!(
+            path.path,
+            Some(PathBuf::from("/slow/ENVPATH_TEST_SLOW_PROBE/app"))
+        );
+
+        let raw = ["$env: home", "app"];
+        let path = EnvPath::from(raw).de_with_timeout(Duration::from_secs(10));
+        assert_eq!(path, EnvPath::new(raw));
+    }
+}
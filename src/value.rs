@@ -1,12 +1,42 @@
-use crate::{EnvPath, OsCow};
+use crate::{parser::ParseCtx, EnvPath, OsCow};
 use std::ops::ControlFlow;
 
 impl EnvPath<'_> {
     /// This function is used to resolve ident in `$val: ident`.
     /// Unlike `$const:`, most of the values here are obtained at runtime.
-    pub(crate) fn match_values(ident: &str) -> OsCow {
+    ///
+    /// `ctx` is used by the idents that read the environment (e.g. `env * home`).
+    pub(crate) fn match_values<'a>(ident: &'a str, ctx: ParseCtx) -> OsCow<'a> {
         match ident {
             "empty" => crate::os_cow::from_str(""),
+            "epoch" => crate::os_cow::into_os_cow(get_epoch()?.as_secs().to_string()),
+            "epoch-ms" | "epoch_ms" => {
+                crate::os_cow::into_os_cow(get_epoch()?.as_millis().to_string())
+            }
+            "nproc" => std::thread::available_parallelism()
+                .ok()
+                .and_then(|n| crate::os_cow::into_os_cow(n.to_string())),
+            // e.g. `rand-16:upper`
+            #[cfg(feature = "rand")]
+            x if x.starts_with("rand-") && x.contains(':') => {
+                let (rand, modifier) = x.rsplit_once(':')?;
+                let value = Self::match_values(rand.trim(), ctx)?;
+                let value = value.to_str()?;
+
+                crate::os_cow::into_os_cow(match modifier.trim() {
+                    "upper" => value.to_ascii_uppercase(),
+                    "lower" => value.to_ascii_lowercase(),
+                    _ => return None,
+                })
+            }
+            #[cfg(feature = "rand")]
+            x if x.starts_with("rand-base32") => crate::os_cow::into_os_cow(
+                crate::random::get_random_base32(Self::get_rand_length(x)),
+            ),
+            #[cfg(feature = "rand")]
+            x if x.starts_with("rand-base64url") => crate::os_cow::into_os_cow(
+                crate::random::get_random_base64url(Self::get_rand_length(x)),
+            ),
             #[cfg(feature = "rand")]
             x if x.starts_with("rand-") => {
                 let u = x
@@ -15,23 +45,74 @@ impl EnvPath<'_> {
                     .and_then(|x| x.parse::<usize>().ok());
                 crate::os_cow::into_os_cow(crate::random::get_random_value(u))
             }
-            x if Self::starts_with_remix_expr(x) => Self::parse_remix_expr(x),
+            x if x.starts_with("base64url(") && x.ends_with(')') => {
+                let name = x["base64url(".len()..x.len() - 1].trim();
+                // Similar to `$env`, but the name is used exactly as written.
+                let value = Self::lookup_env(name, ctx)?;
+                crate::os_cow::into_os_cow(encode_base64url(value.as_encoded_bytes()))
+            }
+            x if Self::starts_with_remix_expr(x) => Self::parse_remix_expr(x, ctx),
             _ => None,
         }
     }
 
-    pub(crate) fn handle_values(ident: &str) -> OsCow {
+    /// Whether `ident` is a known ident of `$val`, even if its value is unavailable on this machine (e.g. `nproc`, or `base64url(NAME)` if `NAME` is unset).
+    ///
+    /// It mirrors the arms of `match_values()`, so a remix expression (e.g. `env * home`) is not included.
+    pub(crate) fn is_known_value(ident: &str) -> bool {
+        match ident {
+            "empty" | "epoch" | "epoch-ms" | "epoch_ms" | "nproc" => true,
+            // e.g. `rand-16:upper`
+            #[cfg(feature = "rand")]
+            x if x.starts_with("rand-") => x
+                .rsplit_once(':')
+                .is_none_or(|(_, m)| matches!(m.trim(), "upper" | "lower")),
+            x => x.starts_with("base64url(") && x.ends_with(')'),
+        }
+    }
+
+    /// Gets the `[usize]` at the end of `rand-base32-[usize]`.
+    #[cfg(feature = "rand")]
+    fn get_rand_length(ident: &str) -> Option<usize> {
+        ident
+            .rsplit_once('-')
+            .and_then(|x| x.1.trim().parse().ok())
+    }
+
+    pub(crate) fn handle_values<'a>(ident: &'a str, ctx: ParseCtx) -> OsCow<'a> {
         use ControlFlow::{Break, Continue};
 
         match Self::get_question_mark_separator(ident) {
-            sep if sep == ' ' => Self::match_values(ident),
-            sep => match Self::parse_dir_rules(ident, Self::match_values, sep) {
+            ' ' => Self::match_values(ident, ctx),
+            sep => match Self::parse_dir_rules(
+                ident,
+                |x| Self::match_values(x, ctx),
+                sep,
+                ctx,
+            ) {
                 Break(x) | Continue(x) => x,
             },
         }
     }
 }
 
+/// The duration since the Unix epoch, which is used by `$val: epoch` and `$val: epoch-ms`.
+///
+/// Returns None if the system clock is set before the epoch.
+fn get_epoch() -> Option<std::time::Duration> {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now().duration_since(UNIX_EPOCH).ok()
+}
+
+/// Encodes `bytes` as base64url (RFC 4648, URL and filename safe), without padding.
+///
+/// It is used by `$val: base64url(NAME)`.
+fn encode_base64url(bytes: &[u8]) -> String {
+    use crate::encoding::{encode, BASE64URL_ALPHABET};
+    encode(bytes, BASE64URL_ALPHABET, 6)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,4 +125,129 @@ mod tests {
         let p = EnvPath::new(["$const: empty ?? val * rand-33"]);
         dbg!(p.display());
     }
+
+    #[test]
+    #[cfg(feature = "rand")]
+    fn rand_case_modifier() {
+        let de = |ident| {
+            EnvPath::match_values(ident, ParseCtx::default())
+                .and_then(|x| x.to_str().map(String::from))
+                .expect("Failed to get the random value")
+        };
+
+        for _ in 0..8 {
+            let upper = de("rand-16:upper");
+            assert_eq!(upper.len(), 16);
+            assert!(!upper.chars().any(|c| c.is_ascii_lowercase()));
+
+            let lower = de("rand-base32-12 : lower");
+            assert_eq!(lower.len(), 12);
+            assert!(!lower.chars().any(|c| c.is_ascii_uppercase()));
+        }
+
+        assert_eq!(
+            EnvPath::match_values("rand-16:title", ParseCtx::default()),
+            None
+        );
+
+        let p = EnvPath::new(["$val: rand-8:lower"]);
+        assert_eq!(p.to_str().map(str::len), Some(8));
+    }
+
+    #[test]
+    fn base64url_env() {
+        use std::env;
+
+        assert_eq!(encode_base64url(b""), "");
+        assert_eq!(encode_base64url(b"hi"), "aGk");
+        assert_eq!(encode_base64url(b"a/b?"), "YS9iPw");
+        assert_eq!(encode_base64url(b"\xfb\xff\xbf"), "-_-_");
+
+        env::set_var("ENVPATH_TEST_APP_TOKEN", "a/b?");
+        let p = EnvPath::new(["$val: base64url(ENVPATH_TEST_APP_TOKEN)", "a"]);
+        assert_eq!(p.path, Some(["YS9iPw", "a"].iter().collect()));
+
+        assert_eq!(
+            EnvPath::match_values(
+                "base64url(ENVPATH_TEST_APP_TOKEN_UNSET)",
+                ParseCtx::default()
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn known_values() {
+        for ident in ["empty", "epoch", "epoch_ms", "nproc", "base64url(ENVPATH_TEST_UNSET)"] {
+            assert!(EnvPath::is_known_value(ident), "{ident}");
+        }
+        for ident in ["none_such", "base64url(", "env * home"] {
+            assert!(!EnvPath::is_known_value(ident), "{ident}");
+        }
+
+        #[cfg(feature = "rand")]
+        {
+            assert!(EnvPath::is_known_value("rand-16:upper"));
+            assert!(!EnvPath::is_known_value("rand-16:title"));
+        }
+    }
+
+    #[test]
+    fn base64url_env_of_ctx() {
+        use std::{collections::HashMap, ffi::OsString};
+
+        let map = HashMap::from([("APP_TOKEN".to_owned(), OsString::from("hi"))]);
+        let resolver = |name: &str| (name == "FALLBACK").then(|| OsString::from("a/b?"));
+        let ctx = ParseCtx {
+            env_map: Some(&map),
+            env_fallback: Some(&resolver),
+            ..Default::default()
+        };
+        let de = |ident| {
+            EnvPath::match_values(ident, ctx).map(|x| x.to_string_lossy().into_owned())
+        };
+
+        assert_eq!(de("base64url(APP_TOKEN)").as_deref(), Some("aGk"));
+        assert_eq!(de("base64url(FALLBACK)").as_deref(), Some("YS9iPw"));
+        // The map replaces the process environment.
+        assert_eq!(de("base64url(PATH)"), None);
+
+        let p = EnvPath::from(["$val: base64url(APP_TOKEN)"]).de_with_env(&map);
+        assert_eq!(p.to_str(), Some("aGk"));
+    }
+
+    #[test]
+    #[cfg(feature = "dirs")]
+    fn epoch() {
+        let now = get_epoch().expect("The clock is before the epoch");
+        let de = |raw: &str| {
+            EnvPath::new([raw])
+                .to_str()
+                .and_then(|x| x.parse::<u128>().ok())
+                .expect("epoch should be an integer")
+        };
+
+        assert!(de("$val: epoch").abs_diff(now.as_secs() as u128) < 5);
+        assert!(de("$val: epoch-ms").abs_diff(now.as_millis()) < 5000);
+
+        // mid-path
+        let p = EnvPath::new(["$dir: cache", "$val: epoch", "x"]);
+        let epoch = p
+            .parent()
+            .and_then(|x| x.file_name())
+            .and_then(|x| x.to_str())
+            .and_then(|x| x.parse::<u64>().ok())
+            .expect("Failed to get the epoch");
+        assert!(epoch.abs_diff(now.as_secs()) < 5);
+    }
+
+    #[test]
+    fn nproc() {
+        let p = EnvPath::new(["$val: nproc"]);
+        let n = p
+            .to_str()
+            .and_then(|x| x.parse::<usize>().ok())
+            .expect("nproc should be an integer");
+        assert!(n > 0);
+    }
 }
@@ -0,0 +1,70 @@
+use std::{ffi::OsString, os::windows::ffi::OsStringExt, path::PathBuf, slice};
+use windows_sys::{
+    core::GUID,
+    Win32::{
+        Globalization::lstrlenW,
+        System::Com::CoTaskMemFree,
+        UI::Shell::{
+            FOLDERID_Desktop, FOLDERID_Documents, FOLDERID_Downloads, FOLDERID_SavedGames,
+            SHGetKnownFolderPath,
+        },
+    },
+};
+
+/// Gets the path of a known folder (e.g. `FOLDERID_Downloads`) via `SHGetKnownFolderPath`.
+///
+/// Unlike the env vars, it honors the real (localized or redirected) location of the folder. Returns None if the call fails.
+fn known_folder(id: &GUID) -> Option<PathBuf> {
+    let mut ptr = std::ptr::null_mut();
+
+    // Safety: On success, `ptr` points to a null-terminated wide string, which must be freed by `CoTaskMemFree` (even if the call fails).
+    unsafe {
+        let result = SHGetKnownFolderPath(id, 0, 0, &mut ptr);
+        let path = (result == 0).then(|| {
+            let wide = slice::from_raw_parts(ptr, lstrlenW(ptr) as usize);
+            PathBuf::from(OsString::from_wide(wide))
+        });
+        CoTaskMemFree(ptr as _);
+        path
+    }
+}
+
+/// The `Downloads` folder, e.g. `C:\Users\m\Downloads`.
+pub(crate) fn downloads_dir() -> Option<PathBuf> {
+    known_folder(&FOLDERID_Downloads)
+}
+
+/// The `Saved Games` folder, e.g. `C:\Users\m\Saved Games`.
+pub(crate) fn saved_games_dir() -> Option<PathBuf> {
+    known_folder(&FOLDERID_SavedGames)
+}
+
+/// The `Desktop` folder, which may be redirected, e.g. `C:\Users\m\OneDrive\Desktop`.
+pub(crate) fn desktop_dir() -> Option<PathBuf> {
+    known_folder(&FOLDERID_Desktop)
+}
+
+/// The `Documents` folder, which may be redirected, e.g. `C:\Users\m\OneDrive\Documents`.
+pub(crate) fn documents_dir() -> Option<PathBuf> {
+    known_folder(&FOLDERID_Documents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::EnvPath;
+
+    #[test]
+    fn known_folders() {
+        let dl = downloads_dir();
+        assert_eq!(dl, dirs::download_dir());
+        assert_eq!(EnvPath::new(["$dir: downloads"]).path, dl);
+
+        let games = saved_games_dir().expect("Failed to get Saved Games");
+        assert!(games.is_absolute());
+        assert_eq!(EnvPath::new(["$dir: saved-games"]).path, Some(games));
+
+        assert_eq!(EnvPath::new(["$dir: desktop"]).path, desktop_dir());
+        assert_eq!(EnvPath::new(["$dir: doc"]).path, documents_dir());
+    }
+}